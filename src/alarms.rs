@@ -0,0 +1,50 @@
+use crate::structs::MainStruct;
+
+// a period shorter than this trips the short period alarm, in s
+const SHORT_PERIOD: f32 = 20.0;
+// startup rate alarm, in decades per minute
+const HIGH_STARTUP_RATE: f32 = 1.0;
+
+pub fn alarms(mainstruct: &mut MainStruct) {
+    let data = &mainstruct.data;
+    let orm = &mainstruct.core.orm;
+    // each alarm with the reading that tripped it
+    let checks = [
+        (
+            "Short period",
+            data.period > 0.0 && data.period < SHORT_PERIOD,
+            format!("{:.1}s", data.period),
+        ),
+        (
+            "High startup rate",
+            data.startup_rate > HIGH_STARTUP_RATE,
+            format!("{:.2}dpm", data.startup_rate),
+        ),
+        (
+            "ORM below minimum",
+            orm.margin < orm.minimum,
+            format!("{:.1} rods", orm.margin),
+        ),
+        (
+            "ORM low",
+            orm.margin >= orm.minimum && orm.margin < orm.warning,
+            format!("{:.1} rods", orm.margin),
+        ),
+    ];
+
+    let mut active = Vec::new();
+    for (name, tripped, reading) in checks {
+        if !tripped {
+            continue;
+        }
+        // log each alarm once as it comes in
+        if !mainstruct.data.alarms.iter().any(|alarm| alarm == name) {
+            mainstruct
+                .data
+                .log
+                .insert(0, format!("ALARM: {} {}", name, reading));
+        }
+        active.push(name.to_string());
+    }
+    mainstruct.data.alarms = active;
+}
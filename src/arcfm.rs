@@ -1,14 +1,18 @@
 use std::collections::HashMap;
 use std::io::Stdout;
 
-use crate::structs::MainStruct;
 use crate::svg::render_svg;
 use lazy_static::lazy_static;
+use rbmk::conduction::{CENTERLINE_LIMIT, CLADDING_LIMIT};
+use rbmk::lar::zone_of;
+use rbmk::layout::{cell_position, CellType};
+use rbmk::structs::{FuelRod, MainStruct, MapView, AXIAL_NODES};
+use rbmk::xenon::xenon_worth;
 use regex::Regex;
 use tui::backend::CrosstermBackend;
 use tui::layout::{Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Style};
-use tui::text::{Spans, Text};
+use tui::text::{Span, Spans};
 use tui::widgets::GraphType::Line as OtherLine;
 use tui::widgets::{Axis, Block, Borders, Chart, Dataset, Paragraph};
 use tui::{symbols, Frame};
@@ -29,120 +33,85 @@ lazy_static! {
 pub type SvgPoints = (Vec<(f64, f64)>, String, bool);
 
 pub fn fuel_rod_table(
-    width: i32,
-    height: i32,
     layout: Rect,
     frame: &mut Frame<CrosstermBackend<Stdout>>,
     mainstruct: &mut MainStruct,
 ) {
-    let column_constraints = std::iter::repeat(Constraint::Percentage((100 / width) as u16))
-        .take((width) as usize)
-        .collect::<Vec<_>>();
-    let row_constraints = std::iter::repeat(Constraint::Percentage((100 / height) as u16))
-        .take((height) as usize)
-        .collect::<Vec<_>>();
+    let width = mainstruct.core.width as usize;
+    let selected = mainstruct.core.selected_rod;
+    let map_view = mainstruct.data.map_view;
+    // two rows of cells to a line, the upper half block takes the colour of the upper cell
+    let mut lines: Vec<Spans> = mainstruct
+        .absorber_rods
+        .chunks(2)
+        .enumerate()
+        .map(|(pair, rows)| {
+            Spans::from(
+                (0..width)
+                    .map(|j| {
+                        let upper =
+                            cell_color(&rows[0][j], 2 * pair * width + j == selected, map_view);
+                        let lower = rows.get(1).map_or(Color::Reset, |row| {
+                            cell_color(&row[j], (2 * pair + 1) * width + j == selected, map_view)
+                        });
+                        Span::styled("▀", Style::default().fg(upper).bg(lower))
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
 
-    let row_rects = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints(row_constraints)
+    // the cells are too small for numbers, so name the hottest channel under the map
+    let hottest = mainstruct
+        .absorber_rods
+        .iter()
+        .flatten()
+        .enumerate()
+        .filter(|(_, rod)| rod.cell_type.is_fuel())
+        .map(|(number, rod)| (number, map_temperature(rod, mainstruct.data.map_view)))
+        .max_by(|a, b| a.1 .0.total_cmp(&b.1 .0));
+    if let Some((number, (value, limit))) = hottest {
+        lines.push(Spans::from(format!(
+            "Hottest: {}:{:.1}°C{}",
+            number + 1,
+            value,
+            if value > limit { "!" } else { "" }
+        )));
+    }
+
+    let map_rect = Layout::default()
+        .constraints([Constraint::Percentage(100)])
         .margin(2)
         .split(layout);
-
-    for i in 0..height {
-        let column_rects = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(column_constraints.clone())
-            .split(row_rects[i as usize]);
-        for j in 0..width {
-            if i > 0 {
-                mainstruct.absorber_rods[i as usize][j as usize].neighbors.1[0] = true;
-                mainstruct.absorber_rods[i as usize][j as usize].neighbors.0[0] =
-                    ((i - 1) as u16, j as u16);
-            }
-            if i < height - 1 {
-                mainstruct.absorber_rods[i as usize][j as usize].neighbors.1[1] = true;
-                mainstruct.absorber_rods[i as usize][j as usize].neighbors.0[1] =
-                    ((i + 1) as u16, j as u16);
-            }
-
-            if j > 0 {
-                mainstruct.absorber_rods[i as usize][j as usize].neighbors.1[2] = true;
-                mainstruct.absorber_rods[i as usize][j as usize].neighbors.0[2] =
-                    (i as u16, (j - 1) as u16);
-            }
-            if j < width - 1 {
-                mainstruct.absorber_rods[i as usize][j as usize].neighbors.1[3] = true;
-                mainstruct.absorber_rods[i as usize][j as usize].neighbors.0[3] =
-                    (i as u16, (j + 1) as u16);
-            }
-            let neighbor_temp_sum = neighbor_temp_sum_fn(height, width, mainstruct, i, j);
-
-            //mainstruct.data.log.push(format!("{:?}, {:?}", mainstruct.absorber_rods[0][0].neighbors, mainstruct.absorber_rods[1][1].neighbors));
-
-            //mainstruct.data.log.push(format!("Temprature: {}, rgba: {:?}", 100.0-temperature, rgba));
-
-            //let text = Text::from(format!("{}:{:.1}%", i*width+j+1,mainstruct.absorber_rods[i as usize][j as usize].absorber_rod_position));
-            let text = Text::from(format!(
-                "{}:{:.1}°C",
-                i * width + j + 1,
-                mainstruct.absorber_rods[i as usize][j as usize].fuel_temperature
-                    + (neighbor_temp_sum as f32 * 0.05)
-            ));
-
-            let cell_text = Paragraph::new(text).block(
-                Block::default().borders(Borders::NONE).style(
-                    Style::default()
-                        .bg(mainstruct.absorber_rods[i as usize][j as usize].temperature_color),
-                ),
-            );
-            frame.render_widget(cell_text, column_rects[j as usize]);
-        }
-    }
+    frame.render_widget(Paragraph::new(lines), map_rect[0]);
 }
 
-fn neighbor_temp_sum_fn(
-    height: i32,
-    width: i32,
-    mainstruct: &mut MainStruct,
-    i: i32,
-    j: i32,
-) -> f64 {
-    const MIN: f64 = 0.0;
-    const MAX: f64 = 100.0;
-    // sum of neighbor temperatures times by 0.05
-    //mainstruct.data.log.push(format!("{}, {}",i+1,j+1));
-    let mut adjusted_height = height;
-    let mut adjusted_width = width;
-    if height % 2 != 0 {
-        adjusted_height = height + 1;
+/// Colour of a cell on the core map, fuel by temperature and rods brighter the further in.
+fn cell_color(rod: &FuelRod, selected: bool, map_view: MapView) -> Color {
+    if selected {
+        return Color::White;
     }
-    if width % 2 != 0 {
-        adjusted_width = width + 1;
+    let shade = |red: f32, green: f32, blue: f32| {
+        let brightness = 0.35 + 0.6 * rod.absorber_rod_position.clamp(0.0, 100.0) / 100.0;
+        Color::Rgb(
+            (red * brightness) as u8,
+            (green * brightness) as u8,
+            (blue * brightness) as u8,
+        )
+    };
+    match rod.cell_type {
+        CellType::Fuel => match temperature_color(rod, map_view) {
+            Color::Reset => Color::Rgb(40, 40, 40),
+            color => color,
+        },
+        CellType::ManualRod => shade(230.0, 230.0, 230.0),
+        CellType::AutomaticRegulator => shade(0.0, 220.0, 255.0),
+        CellType::LocalRegulator => shade(120.0, 120.0, 255.0),
+        CellType::ShortenedAbsorber => shade(0.0, 230.0, 80.0),
+        CellType::EmergencyRod => shade(255.0, 0.0, 255.0),
+        CellType::Detector => Color::Blue,
+        CellType::Empty => Color::Reset,
     }
-    let center = (adjusted_height / 2, adjusted_width / 2);
-    //mainstruct.data.log.push(format!("{}, {}",center.0,center.1));
-    let absorber_rod = &mainstruct.absorber_rods[i as usize][j as usize];
-    let neighbor_temp_sum = absorber_rod
-        .neighbors
-        .1
-        .iter()
-        .enumerate()
-        .filter_map(|(k, active)| {
-            if *active {
-                let pos = absorber_rod.neighbors.0[k];
-                let abs_rod_pos =
-                    mainstruct.absorber_rods[pos.0 as usize][pos.1 as usize].absorber_rod_position;
-                let fuel_temp = ((MAX - MIN) * (1.0 - abs_rod_pos as f64 / 100.0) + MIN).round();
-                let distance: f64 = ((i as f64 + 1.0 - center.0 as f64).abs().powf(2.0)
-                    + (j as f64 + 1.0 - center.1 as f64).abs().powf(2.0))
-                .sqrt();
-                Some(fuel_temp * -0.05 * distance)
-            } else {
-                None
-            }
-        })
-        .sum();
-    neighbor_temp_sum
 }
 
 pub fn fuel_rod_svg(
@@ -165,29 +134,36 @@ pub fn fuel_rod_svg(
     let footer = r#"</svg>"#;
     let selected_fuel_rod = mainstruct.core.selected_rod;
 
-    let mut pos = (0, 0);
-    for i in 0..mainstruct.absorber_rods.len() {
-        for j in 0..mainstruct.absorber_rods[i].len() {
-            if i * 5 + j == selected_fuel_rod {
-                pos = (i, j);
-            }
-        }
-    }
+    let pos = cell_position(mainstruct, selected_fuel_rod + 1).unwrap_or((0, 0));
     mainstruct
         .data
         .log
         .push(format!("{:?}", (pos.0 + 1, pos.1 + 1)));
 
-    let abs_rod_pos = mainstruct.absorber_rods[pos.0][pos.0].absorber_rod_position / 2.0 + 15.0;
+    let abs_rod_pos = mainstruct.absorber_rods[pos.0][pos.1].absorber_rod_position / 2.0 + 15.0;
     let absorber_rod = format!(
         r#"<path d="M 50.000 10.000 L 50.000 {abs_rod_pos}.000" style="stroke: rgb(0, 0, 0); stroke-width: 1; fill: none;" />"#
     );
     let fuel_rod_container = r#"<rect x="37" y="25" width="25" height="60" style="fill: rgb(255, 0, 0); stroke-width: 3; stroke: rgb(0,0,0);" />"#;
+    // axial flux across the fuel, top of the core at the top, three times the mean fills the width
+    let mut axial_shape = String::new();
+    let nodes = &mainstruct.absorber_rods[pos.0][pos.1].nodes;
+    for (k, node) in nodes.iter().enumerate() {
+        let x = 37.0 + 25.0 * (node.flux / 3.0).clamp(0.0, 1.0);
+        let y = 25.0 + 60.0 * (k as f32 + 0.5) / AXIAL_NODES as f32;
+        let command = if k == 0 { "M" } else { "L" };
+        axial_shape.push_str(&format!("{command} {x:.3} {y:.3} "));
+    }
+    let axial_shape = format!(
+        r#"<path d="{}" style="stroke: rgb(255, 255, 0); stroke-width: 1; fill: none;" />"#,
+        axial_shape.trim_end()
+    );
     let mut fuel_rod_svg = String::new();
     fuel_rod_svg.push_str(header_1);
     fuel_rod_svg.push_str(header_2);
     fuel_rod_svg.push_str(fuel_rod_container);
     fuel_rod_svg.push_str(absorber_rod.as_str());
+    fuel_rod_svg.push_str(axial_shape.as_str());
     fuel_rod_svg.push_str(footer);
     //save svg to file
 
@@ -275,21 +251,78 @@ pub fn fuel_rod_svg(
         );
     frame.render_widget(fuel_rod, layout);
     let fuel_rod = Paragraph::new(vec![
-        Spans::from(format!("Fuel rod: {}", selected_fuel_rod + 1)),
+        Spans::from(format!(
+            "Cell {}: {}",
+            selected_fuel_rod + 1,
+            mainstruct.absorber_rods[pos.0][pos.1].cell_type.name()
+        )),
+        Spans::from(
+            match zone_of(&mainstruct.core.local_regulation.zones, pos) {
+                Some(zone) => format!(
+                    "LAR zone {}: {:.2}",
+                    zone + 1,
+                    mainstruct.core.local_regulation.zones[zone].reading
+                ),
+                None => "No LAR zone".to_string(),
+            },
+        ),
         Spans::from(format!(
             "Fuel temp: {:.1}°C",
             mainstruct.absorber_rods[pos.0][pos.1].fuel_temperature
         )),
+        Spans::from(format!(
+            "Centerline: {:.1}°C",
+            mainstruct.absorber_rods[pos.0][pos.1].centerline_temperature
+        )),
+        Spans::from(format!(
+            "Cladding: {:.1}°C",
+            mainstruct.absorber_rods[pos.0][pos.1].cladding_temperature
+        )),
         Spans::from(format!(
             "C-Rod pos: {:.1}%",
             mainstruct.absorber_rods[pos.0][pos.1].absorber_rod_position
         )),
+        Spans::from(format!(
+            "Power: {:.2}MW",
+            mainstruct.absorber_rods[pos.0][pos.1].thermal_power_output
+        )),
+        Spans::from(format!(
+            "Decay heat: {:.2}MW",
+            mainstruct.absorber_rods[pos.0][pos.1].decay_heat
+        )),
+        Spans::from(format!(
+            "Local rho: {:.2}%",
+            mainstruct.absorber_rods[pos.0][pos.1].reactivity * 100.0
+        )),
+        Spans::from(format!(
+            "Xe worth: {:.2}%",
+            xenon_worth(mainstruct.absorber_rods[pos.0][pos.1].xenon) * 100.0
+        )),
+        Spans::from(format!(
+            "Void: {:.1}%",
+            mainstruct.absorber_rods[pos.0][pos.1].void_fraction * 100.0
+        )),
+        Spans::from(format!(
+            "Graphite: {:.1}°C",
+            mainstruct.absorber_rods[pos.0][pos.1].graphite_temperature
+        )),
+        Spans::from(format!(
+            "Burnup: {:.2}MWd/kgU",
+            mainstruct.absorber_rods[pos.0][pos.1].burnup
+        )),
+        Spans::from(format!(
+            "Axial offset: {:.1}%",
+            axial_offset(&mainstruct.absorber_rods[pos.0][pos.1]) * 100.0
+        )),
     ])
     .block(
         Block::default()
             .borders(Borders::ALL)
             .title("Data")
-            .style(Style::default().bg(mainstruct.absorber_rods[pos.0][pos.1].temperature_color)),
+            .style(Style::default().bg(temperature_color(
+                &mainstruct.absorber_rods[pos.0][pos.1],
+                mainstruct.data.map_view,
+            ))),
     );
     // render text in the top right corner
     let text_chunk = Layout::default()
@@ -318,30 +351,42 @@ pub fn fuel_rod_svg(
     frame.render_widget(fuel_rod, vert_alignment[1]);
 }
 
-pub fn temperature(mainstruct: &mut MainStruct, width: i32, height: i32) {
+/// Top half less bottom half of the channel power over the total, positive when top peaked.
+fn axial_offset(rod: &FuelRod) -> f32 {
+    let (top, bottom) = rod.nodes.split_at(AXIAL_NODES / 2);
+    let top: f32 = top.iter().map(|node| node.flux).sum();
+    let bottom: f32 = bottom.iter().map(|node| node.flux).sum();
+    if top + bottom > 0.0 {
+        (top - bottom) / (top + bottom)
+    } else {
+        0.0
+    }
+}
+
+/// The temperature the core map shows for a channel and the limit it is coloured against.
+fn map_temperature(rod: &FuelRod, map_view: MapView) -> (f32, f32) {
+    const FUEL_HOT: f32 = 1200.0; // C
+    match map_view {
+        MapView::Fuel => (rod.fuel_temperature, FUEL_HOT),
+        MapView::Centerline => (rod.centerline_temperature, CENTERLINE_LIMIT),
+        MapView::Cladding => (rod.cladding_temperature, CLADDING_LIMIT),
+    }
+}
+
+/// Colour of a channel on the gradient from cold to the limit of the map, Reset when cold.
+fn temperature_color(rod: &FuelRod, map_view: MapView) -> Color {
     const MIN: f64 = 0.0;
     const MAX: f64 = 100.0;
+    const COLD: f64 = 20.0; // C
 
-    for i in 0..mainstruct.absorber_rods.len() {
-        for j in 0..mainstruct.absorber_rods[0].len() {
-            let temperature = ((MAX - MIN)
-                * (1.0
-                    - mainstruct.absorber_rods[i][j].fuel_temperature as f64
-                        / 600.0)
-                + MIN
-                + (neighbor_temp_sum_fn(height, width, mainstruct, i as i32, j as i32) * 0.05))
-                .round();
-            let rgba = COLOR_GRADIENT.at(100.0 - temperature).to_rgba8();
-            let temperature_color;
-            if temperature == 100.0 {
-                temperature_color = Color::Reset;
-                mainstruct.absorber_rods[i][j].temperature_color =
-                    temperature_color;
-            } else {
-                temperature_color = Color::Rgb(rgba[0], rgba[1], rgba[2]);
-                mainstruct.absorber_rods[i][j].temperature_color =
-                    temperature_color;
-            }
-        }
+    let (value, hot) = map_temperature(rod, map_view);
+    let (value, hot) = (value as f64, hot as f64);
+    let temperature =
+        ((MAX - MIN) * (1.0 - ((value - COLD) / (hot - COLD)).clamp(0.0, 1.0)) + MIN).round();
+    if temperature == 100.0 {
+        Color::Reset
+    } else {
+        let rgba = COLOR_GRADIENT.at(100.0 - temperature).to_rgba8();
+        Color::Rgb(rgba[0], rgba[1], rgba[2])
     }
 }
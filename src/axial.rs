@@ -0,0 +1,225 @@
+use rayon::prelude::*;
+
+use crate::burnup::burnup_worth;
+use crate::geometry::CORE_HEIGHT;
+use crate::interpolate::doppler_worth;
+use crate::level::{enabled, Subsystem};
+use crate::moderator::graphite_worth;
+use crate::rods::group_node_worth;
+use crate::steam::void_worth;
+use crate::structs::{AxialNode, FuelRod, MainStruct, AXIAL_NODES};
+use crate::xenon::xenon_worth;
+
+// one group constants of the lattice along a channel, the migration area in cm^2
+const K_INFINITY: f64 = 1.12;
+const MIGRATION_AREA: f64 = 574.0;
+// linear extrapolation distance past the ends of the fuel, in cm
+const EXTRAPOLATION: f64 = 2.0;
+// power iterations per call, the shape carries over between calls
+const ITERATIONS: usize = 10;
+// reactivity a fully inserted rod takes out of each cell around it, in dk/k,
+// every cell of the lattice has one rod among its eight neighbours
+pub const ROD_WORTH: f64 = 0.15;
+
+/// Solve the axial flux shape of every channel from the reactivity of its nodes,
+/// then set the reactivity of the channel as seen by the radial solve.
+pub fn axial_flux(mainstruct: &mut MainStruct) {
+    let node_height = CORE_HEIGHT * 100.0 / AXIAL_NODES as f64;
+    let void_coefficient = mainstruct.core.void_coefficient;
+    let doppler_coefficient = mainstruct.core.doppler_coefficient;
+    let graphite_coefficient = mainstruct.moderator.temperature_coefficient;
+    // below its level the fuel is taken as fresh, whatever it went through before
+    let depleted = enabled(mainstruct, Subsystem::Burnup);
+
+    // the rods sit in channels of their own, so each works on the cells around it
+    let height = mainstruct.absorber_rods.len();
+    let width = mainstruct.absorber_rods[0].len();
+    let rod_terms: Vec<Vec<Option<[f64; AXIAL_NODES]>>> = mainstruct
+        .absorber_rods
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|rod| {
+                    rod.cell_type.rod_group().map(|group| {
+                        let mut term = [0.0; AXIAL_NODES];
+                        for (k, value) in term.iter_mut().enumerate() {
+                            *value =
+                                -ROD_WORTH * group_node_worth(group, rod.absorber_rod_position, k);
+                        }
+                        term
+                    })
+                })
+                .collect()
+        })
+        .collect();
+
+    mainstruct
+        .absorber_rods
+        .par_iter_mut()
+        .enumerate()
+        .for_each(|(i, row)| {
+            row.iter_mut().enumerate().for_each(|(j, rod)| {
+                let mut rods = [0.0; AXIAL_NODES];
+                let neighbours = rod_terms[i.saturating_sub(1)..(i + 2).min(height)]
+                    .iter()
+                    .flat_map(|terms| &terms[j.saturating_sub(1)..(j + 2).min(width)])
+                    .flatten();
+                for term in neighbours {
+                    for (total, value) in rods.iter_mut().zip(term) {
+                        *total += value;
+                    }
+                }
+                let graphite = graphite_worth(graphite_coefficient, rod.graphite_temperature);
+                let burnup = if depleted {
+                    burnup_worth(&rod.isotopes)
+                } else {
+                    0.0
+                };
+                let fuel = rod.cell_type.is_fuel();
+                for (k, node) in rod.nodes.iter_mut().enumerate() {
+                    // only the fuel feeds back, the rest of the cells just take up the rod shape
+                    let feedback = if fuel {
+                        xenon_worth(node.xenon)
+                            + void_worth(void_coefficient, node.void_fraction)
+                            + doppler_worth(doppler_coefficient, node.fuel_temperature)
+                            + graphite
+                            + burnup
+                    } else {
+                        0.0
+                    };
+                    node.rod_reactivity = rods[k] as f32;
+                    node.reactivity = (rods[k] + feedback) as f32;
+                }
+                solve_channel(&mut rod.nodes, node_height);
+
+                // the flux is its own adjoint in one group, so weight by its square
+                let (weighted, total) = rod.nodes.iter().fold((0.0, 0.0), |acc, node| {
+                    let importance = (node.flux * node.flux) as f64;
+                    (
+                        acc.0 + importance * node.reactivity as f64,
+                        acc.1 + importance,
+                    )
+                });
+                rod.reactivity = if total > 0.0 {
+                    (weighted / total) as f32
+                } else {
+                    0.0
+                };
+            });
+        });
+}
+
+/// Power iterations on the finite difference balance along one channel,
+/// leaving the flux of the nodes normalized to a mean of 1.
+fn solve_channel(nodes: &mut [AxialNode; AXIAL_NODES], node_height: f64) {
+    let coupling = MIGRATION_AREA / (node_height * node_height);
+    let edge = MIGRATION_AREA / (node_height * (node_height / 2.0 + EXTRAPOLATION));
+    let multiplication: [f64; AXIAL_NODES] =
+        std::array::from_fn(|k| K_INFINITY * (1.0 + nodes[k].reactivity as f64).max(0.05));
+
+    // the tridiagonal matrix is the same every iteration
+    let lower = [-coupling; AXIAL_NODES];
+    let upper = [-coupling; AXIAL_NODES];
+    let mut diagonal = [1.0 + 2.0 * coupling; AXIAL_NODES];
+    diagonal[0] = 1.0 + coupling + edge;
+    diagonal[AXIAL_NODES - 1] = 1.0 + coupling + edge;
+
+    // fixed size arrays keep the thousands of small solves off the heap
+    let mut flux: [f64; AXIAL_NODES] = std::array::from_fn(|k| nodes[k].flux as f64);
+    for _ in 0..ITERATIONS {
+        let source: [f64; AXIAL_NODES] = std::array::from_fn(|k| flux[k] * multiplication[k]);
+        flux = thomas(&lower, &diagonal, &upper, &source);
+        let average = flux.iter().sum::<f64>() / AXIAL_NODES as f64;
+        if average <= 0.0 {
+            flux = [1.0; AXIAL_NODES];
+            break;
+        }
+        for value in flux.iter_mut() {
+            *value /= average;
+        }
+    }
+    for (node, flux) in nodes.iter_mut().zip(flux) {
+        node.flux = flux as f32;
+    }
+}
+
+/// Solve a tridiagonal system by forward elimination and back substitution.
+fn thomas<const N: usize>(
+    lower: &[f64; N],
+    diagonal: &[f64; N],
+    upper: &[f64; N],
+    right: &[f64; N],
+) -> [f64; N] {
+    let n = N;
+    let mut upper_prime = [0.0; N];
+    let mut right_prime = [0.0; N];
+    upper_prime[0] = upper[0] / diagonal[0];
+    right_prime[0] = right[0] / diagonal[0];
+    for i in 1..n {
+        let pivot = diagonal[i] - lower[i] * upper_prime[i - 1];
+        upper_prime[i] = upper[i] / pivot;
+        right_prime[i] = (right[i] - lower[i] * right_prime[i - 1]) / pivot;
+    }
+    let mut solution = [0.0; N];
+    solution[n - 1] = right_prime[n - 1];
+    for i in (0..n - 1).rev() {
+        solution[i] = right_prime[i] - upper_prime[i] * solution[i + 1];
+    }
+    solution
+}
+
+/// Channel average of a node quantity.
+pub fn node_average<F>(rod: &FuelRod, value: F) -> f32
+where
+    F: Fn(&AxialNode) -> f32,
+{
+    rod.nodes.iter().map(value).sum::<f32>() / AXIAL_NODES as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_rods(mainstruct: &mut MainStruct, position: f32) {
+        for row in mainstruct.absorber_rods.iter_mut() {
+            for rod in row.iter_mut() {
+                rod.absorber_rod_position = position;
+            }
+        }
+    }
+
+    fn top_half(rod: &FuelRod) -> f32 {
+        rod.nodes[..AXIAL_NODES / 2]
+            .iter()
+            .map(|node| node.flux)
+            .sum()
+    }
+
+    #[test]
+    fn test_symmetric_without_rods() {
+        let mut mainstruct = MainStruct::default();
+        set_rods(&mut mainstruct, 0.0);
+        for _ in 0..20 {
+            axial_flux(&mut mainstruct);
+        }
+        let rod = &mainstruct.absorber_rods[24][24];
+        let top = top_half(rod);
+        assert!(
+            (top - AXIAL_NODES as f32 / 2.0).abs() < 0.05,
+            "top: {}",
+            top
+        );
+        assert!(rod.nodes[AXIAL_NODES / 2].flux > rod.nodes[0].flux);
+    }
+
+    #[test]
+    fn test_rods_from_top_push_flux_down() {
+        let mut mainstruct = MainStruct::default();
+        set_rods(&mut mainstruct, 40.0);
+        for _ in 0..20 {
+            axial_flux(&mut mainstruct);
+        }
+        let top = top_half(&mainstruct.absorber_rods[24][24]);
+        assert!(top < AXIAL_NODES as f32 / 4.0, "top: {}", top);
+    }
+}
@@ -0,0 +1,155 @@
+use rayon::prelude::*;
+
+use crate::geometry::RODS_PER_CHANNEL;
+use crate::kinetics::flux_weighted;
+use crate::level::{enabled, Subsystem};
+use crate::structs::{FuelPellet, Isotopes, MainStruct};
+
+// mass fraction of uranium in UO2
+const URANIUM_FRACTION: f32 = 0.881;
+const URANIUM_MOLAR_MASS: f64 = 238.0; // g/mol
+const AVOGADRO: f64 = 6.022e23;
+const ENERGY_PER_FISSION: f64 = 3.204e-11; // J, 200 MeV
+const SECONDS_PER_DAY: f64 = 86400.0;
+// effective one group cross sections in barns, U-238 capture includes the resonances
+const U235_ABSORPTION: f64 = 480.0;
+const U235_FISSION: f64 = 400.0;
+const U238_CAPTURE: f64 = 7.0;
+const PU239_ABSORPTION: f64 = 1500.0;
+const PU239_FISSION: f64 = 1000.0;
+const PU240_CAPTURE: f64 = 290.0;
+const PU241_ABSORPTION: f64 = 1380.0;
+const PU241_FISSION: f64 = 1030.0;
+// lumped fission products, per fission
+const FISSION_PRODUCT_ABSORPTION: f64 = 50.0;
+// graphite, water and structure per heavy metal atom
+const OTHER_ABSORPTION: f64 = 12.0;
+// neutrons per fission
+const U235_NU: f64 = 2.43;
+const PU239_NU: f64 = 2.87;
+const PU241_NU: f64 = 2.93;
+
+/// Uranium in one fuel channel, in kg.
+pub fn uranium_mass(fuel_pellet: &FuelPellet) -> f32 {
+    let area = std::f32::consts::PI * fuel_pellet.diameter * fuel_pellet.diameter / 4.0;
+    let volume = area * fuel_pellet.stack_length * 100.0 * RODS_PER_CHANNEL;
+    volume * fuel_pellet.density * URANIUM_FRACTION / 1000.0
+}
+
+pub fn burnup(mainstruct: &mut MainStruct, dt: f32) {
+    // the fuel keeps its composition while burnup is off, and goes on from it when it is back on
+    if !enabled(mainstruct, Subsystem::Burnup) {
+        mainstruct.core.kinetics.reactivity.burnup = 0.0;
+        return;
+    }
+    let mass = uranium_mass(&mainstruct.physical_variables.fuel_rod_data.fuel_pellet);
+    let mass = mass as f64;
+    let heavy_atoms = mass * 1000.0 / URANIUM_MOLAR_MASS * AVOGADRO;
+    let dt = dt as f64;
+
+    mainstruct.absorber_rods.par_iter_mut().for_each(|row| {
+        row.par_iter_mut()
+            .filter(|rod| rod.cell_type.is_fuel())
+            .for_each(|rod| {
+                let channel_power = rod.fission_power as f64;
+                rod.burnup += channel_power * dt / SECONDS_PER_DAY / mass;
+
+                // the power sets the fission rate, which sets the flux seen by every isotope
+                let fission_rate = channel_power * 1.0e6 / ENERGY_PER_FISSION / heavy_atoms;
+                let fission = fission_cross_section(&rod.isotopes);
+                if fission <= 0.0 || fission_rate <= 0.0 {
+                    return;
+                }
+                let flux = fission_rate / fission * dt;
+                let Isotopes {
+                    u235,
+                    u238,
+                    pu239,
+                    pu240,
+                    pu241,
+                    ..
+                } = rod.isotopes;
+                let isotopes = &mut rod.isotopes;
+                isotopes.u235 = (u235 * (1.0 - U235_ABSORPTION * flux)).max(0.0);
+                isotopes.u238 = (u238 * (1.0 - U238_CAPTURE * flux)).max(0.0);
+                isotopes.pu239 =
+                    (pu239 + (U238_CAPTURE * u238 - PU239_ABSORPTION * pu239) * flux).max(0.0);
+                isotopes.pu240 = (pu240
+                    + ((PU239_ABSORPTION - PU239_FISSION) * pu239 - PU240_CAPTURE * pu240) * flux)
+                    .max(0.0);
+                isotopes.pu241 =
+                    (pu241 + (PU240_CAPTURE * pu240 - PU241_ABSORPTION * pu241) * flux).max(0.0);
+                isotopes.fission_products += fission_rate * dt;
+            });
+    });
+
+    mainstruct.core.kinetics.reactivity.burnup =
+        flux_weighted(mainstruct, |rod, _| burnup_worth(&rod.isotopes));
+}
+
+/// Fission cross section per heavy metal atom, in barns.
+fn fission_cross_section(isotopes: &Isotopes) -> f64 {
+    U235_FISSION * isotopes.u235 + PU239_FISSION * isotopes.pu239 + PU241_FISSION * isotopes.pu241
+}
+
+fn multiplication(isotopes: &Isotopes) -> f64 {
+    let production = U235_NU * U235_FISSION * isotopes.u235
+        + PU239_NU * PU239_FISSION * isotopes.pu239
+        + PU241_NU * PU241_FISSION * isotopes.pu241;
+    let absorption = U235_ABSORPTION * isotopes.u235
+        + U238_CAPTURE * isotopes.u238
+        + PU239_ABSORPTION * isotopes.pu239
+        + PU240_CAPTURE * isotopes.pu240
+        + PU241_ABSORPTION * isotopes.pu241
+        + FISSION_PRODUCT_ABSORPTION * isotopes.fission_products
+        + OTHER_ABSORPTION;
+    production / absorption
+}
+
+/// Reactivity of the fuel composition against fresh fuel in dk/k.
+pub fn burnup_worth(isotopes: &Isotopes) -> f64 {
+    let fresh = multiplication(&Isotopes::default());
+    let k = multiplication(isotopes);
+    if k > 0.0 {
+        (k - fresh) / k
+    } else {
+        -1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_mass() {
+        // a little over 110 kg of uranium per channel
+        let mass = uranium_mass(&FuelPellet::default());
+        assert!(mass > 110.0 && mass < 140.0, "mass: {}", mass);
+    }
+
+    #[test]
+    fn test_depletion() {
+        // run every channel at the nominal 2 MW for a thousand days
+        let mut mainstruct = MainStruct::default();
+        for row in mainstruct.absorber_rods.iter_mut() {
+            for rod in row.iter_mut() {
+                rod.fission_power = 2.0;
+                rod.neutron_flux = 1.0;
+            }
+        }
+        for _ in 0..1000 {
+            burnup(&mut mainstruct, SECONDS_PER_DAY as f32);
+        }
+        let rod = &mainstruct.absorber_rods[24][24];
+        assert!(
+            rod.burnup > 14.0 && rod.burnup < 17.0,
+            "burnup: {}",
+            rod.burnup
+        );
+        assert!(rod.isotopes.u235 < Isotopes::default().u235 * 0.7);
+        assert!(rod.isotopes.pu239 > 0.002 && rod.isotopes.pu241 > 0.0);
+        let worth = mainstruct.core.kinetics.reactivity.burnup;
+        assert!(worth < -0.05 && worth > -0.3, "worth: {}", worth);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::alarms::alarms;
+use crate::burnup::burnup;
+use crate::decay::decay_heat;
+use crate::diffusion::diffusion;
+use crate::interpolate::{fuel_temperature, graphline, interpolate_position, turbine};
+use crate::kinetics::point_kinetics;
+use crate::lar::local_regulation;
+use crate::malfunction::{failed_sensors, malfunctions};
+use crate::moderator::graphite_temperature;
+use crate::orm::operational_margin;
+use crate::regulator::automatic_regulator;
+use crate::scenario::scenario;
+use crate::session::replay;
+use crate::startup::neutron_rate;
+use crate::steam::{steam, void_fraction};
+use crate::structs::MainStruct;
+use crate::xenon::xenon;
+
+// fastest the clock may run against wall time
+pub const MAX_SPEED: f32 = 100.0;
+// ticks run between two frames at most, time due beyond that is dropped
+const MAX_TICKS_PER_FRAME: u32 = 100;
+// single steps queued at most, an hour of simulated time at the default dt
+pub const MAX_PENDING_STEPS: u32 = 3600;
+
+/// Advance every model by one step of dt seconds.
+pub fn tick(mainstruct: &mut MainStruct, dt: f32) {
+    replay(mainstruct);
+    scenario(mainstruct);
+    malfunctions(mainstruct, dt);
+    interpolate_position(mainstruct, dt);
+    graphline(mainstruct, dt);
+    diffusion(mainstruct);
+    point_kinetics(mainstruct, dt);
+    neutron_rate(mainstruct);
+    failed_sensors(mainstruct);
+    operational_margin(mainstruct);
+    decay_heat(mainstruct, dt);
+    automatic_regulator(mainstruct, dt);
+    local_regulation(mainstruct, dt);
+    xenon(mainstruct, dt);
+    burnup(mainstruct, dt);
+    void_fraction(mainstruct, dt);
+    fuel_temperature(mainstruct, dt);
+    graphite_temperature(mainstruct, dt);
+    steam(mainstruct, dt);
+    turbine(mainstruct, dt);
+    alarms(mainstruct);
+    mainstruct.clock.time += dt as f64;
+}
+
+/// Run the fixed steps that fall due over `elapsed` seconds of wall time,
+/// along with any single steps asked for while paused.
+pub fn advance(mainstruct: &mut MainStruct, elapsed: f32) {
+    let clock = &mut mainstruct.clock;
+    if !clock.paused {
+        clock.accumulated += elapsed * clock.speed;
+    }
+    let mut ticks = (clock.accumulated / clock.dt) as u32;
+    clock.accumulated -= ticks as f32 * clock.dt;
+    if ticks > MAX_TICKS_PER_FRAME {
+        // the models cannot keep up, so run slower than asked rather than fall further behind
+        ticks = MAX_TICKS_PER_FRAME;
+        clock.accumulated = 0.0;
+    }
+    let steps = clock.pending_steps.min(MAX_TICKS_PER_FRAME - ticks);
+    clock.pending_steps -= steps;
+
+    let dt = clock.dt;
+    for _ in 0..ticks + steps {
+        tick(mainstruct, dt);
+    }
+}
+
+/// Simulated time as hours, minutes and seconds.
+pub fn clock_text(mainstruct: &MainStruct) -> String {
+    let seconds = mainstruct.clock.time as u64;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{send_command, HELP_PAGE_LINES};
+
+    #[test]
+    fn test_speed_and_pause() {
+        let mut mainstruct = MainStruct::default();
+        mainstruct.clock.speed = 5.0;
+        advance(&mut mainstruct, 0.5);
+        assert_eq!(mainstruct.clock.time, 2.0);
+        assert_eq!(mainstruct.clock.accumulated, 0.5);
+
+        mainstruct.clock.paused = true;
+        advance(&mut mainstruct, 10.0);
+        assert_eq!(mainstruct.clock.time, 2.0);
+
+        mainstruct.clock.pending_steps = 3;
+        advance(&mut mainstruct, 0.0);
+        assert_eq!(mainstruct.clock.time, 5.0);
+        assert_eq!(mainstruct.clock.pending_steps, 0);
+        assert_eq!(clock_text(&mainstruct), "0:00:05");
+    }
+
+    #[test]
+    fn test_steps_are_capped() {
+        let mut mainstruct = MainStruct::default();
+        send_command("step 4294967295", &mut mainstruct, HELP_PAGE_LINES);
+        send_command("step 4294967295", &mut mainstruct, HELP_PAGE_LINES);
+        assert_eq!(mainstruct.clock.pending_steps, MAX_PENDING_STEPS);
+        assert!(mainstruct.data.log[0].starts_with("Steps queued capped"));
+    }
+
+    #[test]
+    fn test_rods_move_by_dt() {
+        // the same simulated time moves the rods as far in one step as in ten
+        let mut coarse = MainStruct::default();
+        let mut fine = MainStruct::default();
+        for mainstruct in [&mut coarse, &mut fine] {
+            for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+                rod.absorber_rod_position = 0.0;
+                rod.insert_rod = true;
+                rod.set_point = 100.0;
+            }
+        }
+        interpolate_position(&mut coarse, 1.0);
+        for _ in 0..10 {
+            interpolate_position(&mut fine, 0.1);
+        }
+        let (a, b) = (&coarse.absorber_rods[22][1], &fine.absorber_rods[22][1]);
+        assert!((a.absorber_rod_position - b.absorber_rod_position).abs() < 1.0e-4);
+    }
+}
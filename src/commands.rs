@@ -1,12 +1,27 @@
-use crate::structs::MainStruct;
+use crate::clock::{MAX_PENDING_STEPS, MAX_SPEED};
+use crate::lar::{disable_local_regulation, enable_local_regulation};
+use crate::layout::{cell_position, RodGroup};
+use crate::level::set_level;
+use crate::malfunction::{
+    arm_malfunction, clear_malfunction, list_malfunctions, parse_malfunction,
+};
+use crate::regulator::{disable_regulator, enable_regulator, MAX_SETPOINT};
+use crate::scenario::list_objectives;
+use crate::session::record_command;
+use crate::snapshot::{load_snapshot, save_snapshot};
+use crate::startup::record_inverse_count_rate;
+use crate::structs::{FuelRod, MainStruct, MapView};
 use lazy_static::lazy_static;
 use rayon::prelude::*;
 use regex::{Regex, RegexSet};
 
+// help pages hold this many lines when there is no screen to fit them to
+pub const HELP_PAGE_LINES: u16 = 40;
+
 lazy_static! {
     static ref REGEX_SET: RegexSet = RegexSet::new([
         r"insert rod (\d+)",
-        r"scram",
+        r"^scram",
         r"insert rods",
         r"pull|remove rods",
         r"remove rod (\d+)",
@@ -24,11 +39,42 @@ lazy_static! {
         r"select (\d+)",
         r"enter",
         r"exit",
+        r"coolant flow (\d+)",
+        r"void coefficient (-?\d+)",
+        r"doppler coefficient (-?\d+(?:\.\d+)?)",
+        r"graphite coefficient (-?\d+(?:\.\d+)?)",
+        r"map (fuel|centerline|cladding)",
+        r"source range (reference|record|clear)",
+        r"orm (minimum|warning) (\d+(?:\.\d+)?)",
+        r"reset scram",
+        r"group (rr|ar|lar|usp|az) speed (\d+(?:\.\d+)?)",
+        r"group (rr|ar|lar|usp|az) scram (on|off)",
+        r"auto (on|off)",
+        r"auto power (\d+(?:\.\d+)?)",
+        r"lar (on|off)",
+        r"^pause$",
+        r"^resume$",
+        r"^step (\d+)$",
+        r"^speed (\d+(?:\.\d+)?)x?$",
+        r"^save ([\w-]+)$",
+        r"^load ([\w-]+)$",
+        r"^objectives$",
+        r"^malfunctions$",
+        r"^repair (\d+|all)$",
+        r"^malfunction (.+)$",
+        r"^level(?: (\d+))?$",
     ])
     .unwrap();
 }
 
+/// Apply a command from the operator, recording it when a session is being recorded.
 pub fn send_command(command: &str, mainstruct: &mut MainStruct, height: u16) {
+    record_command(mainstruct, command);
+    run_command(command, mainstruct, height);
+}
+
+/// Apply a command without recording it, for the commands scenarios and replays run themselves.
+pub fn run_command(command: &str, mainstruct: &mut MainStruct, height: u16) {
     let matches = REGEX_SET.matches(command);
     //println!("{:?}", matches);
     if let Some(index) = matches.into_iter().next() {
@@ -37,36 +83,37 @@ pub fn send_command(command: &str, mainstruct: &mut MainStruct, height: u16) {
                 let re = Regex::new(r"insert rod (\d+)").unwrap();
                 let cap = re.captures(command).unwrap();
                 let rod = cap[1].parse::<usize>().unwrap();
-                let amount = mainstruct.absorber_rods.len() * mainstruct.absorber_rods[0].len();
-                if rod > amount {
-                    //println!("rod number too high");
-                } else {
-                    let row = rod / mainstruct.absorber_rods[0].len();
-                    let col = rod % mainstruct.absorber_rods[0].len();
-                    mainstruct.absorber_rods[row][col - 1].insert_rod = true;
+                if let Some((row, col)) = rod_cell(mainstruct, rod) {
+                    mainstruct.absorber_rods[row][col].insert_rod = true;
                 }
             }
             1 => {
-                // set all fuel rods to insert = false
-                for i in 0..mainstruct.absorber_rods.len() {
-                    for j in 0..mainstruct.absorber_rods[i].len() {
-                        mainstruct.absorber_rods[i][j].insert_rod = false;
+                // every group that takes part goes all the way in
+                let drives = mainstruct.core.rod_drives;
+                for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+                    if let Some(group) = rod.cell_type.rod_group() {
+                        if drives[group as usize].scram {
+                            rod.insert_rod = false;
+                        }
                     }
                 }
+                mainstruct.core.scram = true;
             }
             2 => {
-                // set all fuel rods to insert = true
-                for i in 0..mainstruct.absorber_rods.len() {
-                    for j in 0..mainstruct.absorber_rods[i].len() {
-                        mainstruct.absorber_rods[i][j].insert_rod = true;
+                // set the rods of the group to insert = true
+                let group = command_group(command);
+                for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+                    if in_group(rod, group) {
+                        rod.insert_rod = true;
                     }
                 }
             }
             3 => {
-                // set all fuel rods to insert = false
-                for i in 0..mainstruct.absorber_rods.len() {
-                    for j in 0..mainstruct.absorber_rods[i].len() {
-                        mainstruct.absorber_rods[i][j].insert_rod = false;
+                // set the rods of the group to insert = false
+                let group = command_group(command);
+                for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+                    if in_group(rod, group) {
+                        rod.insert_rod = false;
                     }
                 }
             }
@@ -74,23 +121,28 @@ pub fn send_command(command: &str, mainstruct: &mut MainStruct, height: u16) {
                 let re = Regex::new(r"remove rod (\d+)").unwrap();
                 let cap = re.captures(command).unwrap();
                 let rod = cap[1].parse::<usize>().unwrap();
-                let amount = mainstruct.absorber_rods.len() * mainstruct.absorber_rods[0].len();
-                if rod > amount {
-                    //println!("rod number too high");
-                } else {
-                    let row = rod / mainstruct.absorber_rods[0].len();
-                    let col = rod % mainstruct.absorber_rods[0].len();
-                    mainstruct.absorber_rods[row][col - 1].insert_rod = false;
+                if let Some((row, col)) = rod_cell(mainstruct, rod) {
+                    mainstruct.absorber_rods[row][col].insert_rod = false;
                 }
             }
             5 => {
                 let re = Regex::new(r"setpoint (\d+)").unwrap();
                 let cap = re.captures(command).unwrap();
                 let setpoint = cap[1].parse::<f32>().unwrap();
-                // set all fuel rods to setpoint
-                for i in 0..mainstruct.absorber_rods.len() {
-                    for j in 0..mainstruct.absorber_rods[i].len() {
-                        mainstruct.absorber_rods[i][j].set_point = setpoint;
+                // set the rods of the group to setpoint
+                let group = command_group(command);
+                if let Some(group) = group.filter(|group| !group.takes_set_point()) {
+                    let message = format!("{} rods take no set point", group.name());
+                    mainstruct.data.log.insert(0, message);
+                    return;
+                }
+                for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+                    let takes_set_point = rod
+                        .cell_type
+                        .rod_group()
+                        .is_some_and(RodGroup::takes_set_point);
+                    if in_group(rod, group) && takes_set_point {
+                        rod.set_point = setpoint;
                     }
                 }
             }
@@ -98,14 +150,8 @@ pub fn send_command(command: &str, mainstruct: &mut MainStruct, height: u16) {
                 let re = Regex::new(r"set rod (\d+) to (\d+)").unwrap();
                 let cap = re.captures(command).unwrap();
                 let rod = cap[1].parse::<usize>().unwrap();
-                let amount = mainstruct.absorber_rods.len() * mainstruct.absorber_rods[0].len();
-                if rod > amount {
-                    //println!("rod number too high");
-                } else {
-                    let row = rod / mainstruct.absorber_rods[0].len();
-                    let col = rod % mainstruct.absorber_rods[0].len();
-                    mainstruct.absorber_rods[row][col - 1].set_point =
-                        cap[2].parse::<f32>().unwrap();
+                if let Some((row, col)) = rod_cell(mainstruct, rod) {
+                    mainstruct.absorber_rods[row][col].set_point = cap[2].parse::<f32>().unwrap();
                 }
             }
             7 => {
@@ -115,20 +161,49 @@ pub fn send_command(command: &str, mainstruct: &mut MainStruct, height: u16) {
                     "help <page> - display this page",
                     "insert rod <rod number> - insert a fuel rod",
                     "remove rod <rod number> - remove a fuel rod",
-                    "insert rods - insert all fuel rods",
-                    "pull rods - remove all fuel rods",
-                    "scram - remove all fuel rods",
-                    "setpoint <setpoint> - set all fuel rods to a setpoint",
+                    "insert rods [group] - insert all fuel rods, or those of a group",
+                    "pull rods [group] - remove all fuel rods, or those of a group",
+                    "scram - drive every group that takes part in scram fully in",
+                    "reset scram - let the rods be driven out again",
+                    "setpoint <setpoint> [group] - set all fuel rods, or a group, to a setpoint",
                     "set rod <rod number> to <setpoint> - set a fuel rod to a setpoint",
                     "cls - clear the log",
-                    "center core only - insert only the center core",
-                    "setpoint speed <slow|medium|fast> - set the speed of the setpoint change",
+                    "center core only - insert the rods within half the core radius, remove the rest",
+                    "setpoint speed <slow|medium|fast> [group] - set the RR, or a group's, rod speed",
                     "dev sp <position> - change the position of the absorber rods to position",
                     "dev sp speed <number> - set the speed of the absorber rod change",
-                    "hold rods - hold the rods in place",
+                    "hold rods [group] - hold the rods, or those of a group, in place",
                     "drain valve <position> - change the position of the drain valve to position",
                     "select rod <rod number> - select a rod to view its data",
                     "select <number> - select a checklist item",
+                    "coolant flow <percent> - set the main circulation flow",
+                    "void coefficient <pcm> - set the void reactivity per % of void",
+                    "doppler coefficient <pcm> - set the fuel temperature reactivity per C",
+                    "graphite coefficient <pcm> - set the graphite temperature reactivity per C",
+                    "map <fuel|centerline|cladding> - choose the temperature the core map shows",
+                    "source range reference - take the count rate as the 1/M reference",
+                    "source range record - record 1/M against the average rod position",
+                    "source range clear - clear the recorded 1/M points",
+                    "orm minimum <rods> - set the ORM below which the reactor must be shut down",
+                    "orm warning <rods> - set the ORM below which operation is restricted",
+                    "group <rr|ar|lar|usp|az> speed <%/s> - set the drive speed of a rod group",
+                    "group <rr|ar|lar|usp|az> scram <on|off> - choose whether a group scrams",
+                    "auto on - hand the AR rods to the regulator, holding the power",
+                    "auto off - take the AR rods back, leaving them where they are",
+                    "auto power <percent> - set the thermal power the regulator holds, up to 100 %",
+                    "lar on - let the LAR rods flatten the power in their zones",
+                    "lar off - take the LAR rods back, leaving them where they are",
+                    "pause - stop the simulation clock",
+                    "resume - start the simulation clock again",
+                    "step <steps> - run a number of single time steps, also while paused, up to 3600 queued",
+                    "speed <multiplier> - run the clock faster than real time, up to 100x",
+                    "save <name> - save the whole plant state as a snapshot",
+                    "load <name> - go back to a saved snapshot",
+                    "objectives - list the objectives of the scenario and how they stand",
+                    "malfunction <failure> [in <s> | when <quantity> <above|below> <value>] - fail now or later",
+                    "malfunctions - list the failed and armed malfunctions by number",
+                    "repair <number|all> - clear a malfunction, or all of them",
+                    "level [0-3] - show the simulation level, or run the plant at another",
                 ];
                 let re = Regex::new(r"help (\d+)").unwrap();
                 let cap = re.captures(command).unwrap();
@@ -152,21 +227,26 @@ pub fn send_command(command: &str, mainstruct: &mut MainStruct, height: u16) {
                 mainstruct.data.log.clear();
             }
             9 => {
-                // center core only where a 5x5 grid the centre is 3x3
-                let length = mainstruct.absorber_rods.len();
+                // the rods within half the radius of the fuel are inserted, the rest removed
+                const CENTRE_FRACTION: f32 = 0.5;
+                let height = mainstruct.absorber_rods.len();
                 let width = mainstruct.absorber_rods[0].len();
-                for i in 0..length {
-                    if i > 0 && i < (length - 1) {
-                        for j in 0..width {
-                            if j > 0 && j < (length - 1) {
-                                mainstruct.absorber_rods[i][j].insert_rod = true;
-                            } else {
-                                mainstruct.absorber_rods[i][j].insert_rod = false;
-                            }
+                let centre = ((height - 1) as f32 / 2.0, (width - 1) as f32 / 2.0);
+                let distance = |i: usize, j: usize| {
+                    ((i as f32 - centre.0).powi(2) + (j as f32 - centre.1).powi(2)).sqrt()
+                };
+                let mut radius: f32 = 0.0;
+                for (i, row) in mainstruct.absorber_rods.iter().enumerate() {
+                    for (j, cell) in row.iter().enumerate() {
+                        if cell.cell_type.is_fuel() {
+                            radius = radius.max(distance(i, j));
                         }
-                    } else {
-                        for j in 0..width {
-                            mainstruct.absorber_rods[i][j].insert_rod = false;
+                    }
+                }
+                for (i, row) in mainstruct.absorber_rods.iter_mut().enumerate() {
+                    for (j, cell) in row.iter_mut().enumerate() {
+                        if cell.cell_type.is_rod() {
+                            cell.insert_rod = distance(i, j) <= radius * CENTRE_FRACTION;
                         }
                     }
                 }
@@ -176,7 +256,9 @@ pub fn send_command(command: &str, mainstruct: &mut MainStruct, height: u16) {
                 let cap = re.captures(command).unwrap();
                 let speed = cap[1].parse::<String>().unwrap();
 
-                mainstruct.core.speed_setpoint = match speed.as_str() {
+                // the manual rods unless another group is named
+                let group = command_group(command).unwrap_or(RodGroup::Manual);
+                mainstruct.core.rod_drives[group as usize].speed = match speed.as_str() {
                     "slow" => 0.1,
                     "s" => 0.1,
                     "medium" => 0.5,
@@ -197,9 +279,12 @@ pub fn send_command(command: &str, mainstruct: &mut MainStruct, height: u16) {
                 }
             }
             12 => {
+                let group = command_group(command);
                 mainstruct.absorber_rods.par_iter_mut().for_each(|row| {
                     row.par_iter_mut().for_each(|cell| {
-                        cell.set_point = cell.absorber_rod_position;
+                        if in_group(cell, group) {
+                            cell.set_point = cell.absorber_rod_position;
+                        }
                     })
                 });
             }
@@ -213,13 +298,17 @@ pub fn send_command(command: &str, mainstruct: &mut MainStruct, height: u16) {
                 let re = Regex::new(r"select rod (\d+)").unwrap();
                 let cap = re.captures(command).unwrap();
                 let rod = cap[1].parse::<usize>().unwrap();
-                mainstruct.core.selected_rod = rod - 1;
+                if cell_position(mainstruct, rod).is_some() {
+                    mainstruct.core.selected_rod = rod - 1;
+                }
             }
             15 => {
                 let re = Regex::new(r"dev sp speed (\d+)").unwrap();
                 let cap = re.captures(command).unwrap();
                 let speed = cap[1].parse::<f32>().unwrap();
-                mainstruct.core.speed_setpoint = speed;
+                for drive in mainstruct.core.rod_drives.iter_mut() {
+                    drive.speed = speed;
+                }
             }
             16 => {
                 let re = Regex::new(r"select (\d+)").unwrap();
@@ -233,6 +322,224 @@ pub fn send_command(command: &str, mainstruct: &mut MainStruct, height: u16) {
             18 => {
                 mainstruct.data.text_input = true;
             }
+            19 => {
+                let re = Regex::new(r"coolant flow (\d+)").unwrap();
+                let cap = re.captures(command).unwrap();
+                let flow = cap[1].parse::<f32>().unwrap();
+                mainstruct.core.coolant_flow = flow;
+            }
+            20 => {
+                // given in pcm per % of void
+                let re = Regex::new(r"void coefficient (-?\d+)").unwrap();
+                let cap = re.captures(command).unwrap();
+                let coefficient = cap[1].parse::<f32>().unwrap();
+                mainstruct.core.void_coefficient = coefficient * 1.0e-5;
+            }
+            21 => {
+                // given in pcm per C of fuel temperature
+                let re = Regex::new(r"doppler coefficient (-?\d+(?:\.\d+)?)").unwrap();
+                let cap = re.captures(command).unwrap();
+                let coefficient = cap[1].parse::<f32>().unwrap();
+                mainstruct.core.doppler_coefficient = coefficient * 1.0e-5;
+            }
+            22 => {
+                // given in pcm per C of graphite temperature
+                let re = Regex::new(r"graphite coefficient (-?\d+(?:\.\d+)?)").unwrap();
+                let cap = re.captures(command).unwrap();
+                let coefficient = cap[1].parse::<f32>().unwrap();
+                mainstruct.moderator.temperature_coefficient = coefficient * 1.0e-5;
+            }
+            23 => {
+                let re = Regex::new(r"map (fuel|centerline|cladding)").unwrap();
+                let cap = re.captures(command).unwrap();
+                mainstruct.data.map_view = match &cap[1] {
+                    "centerline" => MapView::Centerline,
+                    "cladding" => MapView::Cladding,
+                    _ => MapView::Fuel,
+                };
+            }
+            24 => {
+                let re = Regex::new(r"source range (reference|record|clear)").unwrap();
+                let cap = re.captures(command).unwrap();
+                match &cap[1] {
+                    "reference" => {
+                        mainstruct.source_range.reference_count_rate =
+                            mainstruct.source_range.count_rate;
+                        mainstruct.source_range.inverse_count_rate.clear();
+                    }
+                    "record" => record_inverse_count_rate(mainstruct),
+                    _ => {
+                        mainstruct.source_range.reference_count_rate = 0.0;
+                        mainstruct.source_range.inverse_count_rate.clear();
+                    }
+                }
+            }
+            25 => {
+                // given in equivalent fully inserted rods
+                let re = Regex::new(r"orm (minimum|warning) (\d+(?:\.\d+)?)").unwrap();
+                let cap = re.captures(command).unwrap();
+                let rods = cap[2].parse::<f32>().unwrap();
+                match &cap[1] {
+                    "minimum" => mainstruct.core.orm.minimum = rods,
+                    _ => mainstruct.core.orm.warning = rods,
+                }
+            }
+            26 => {
+                // the rods stay in until they are driven out again
+                mainstruct.core.scram = false;
+            }
+            27 => {
+                // given in % of the travel per s
+                let re = Regex::new(r"group (rr|ar|lar|usp|az) speed (\d+(?:\.\d+)?)").unwrap();
+                let cap = re.captures(command).unwrap();
+                let group = RodGroup::from_name(&cap[1]).unwrap();
+                mainstruct.core.rod_drives[group as usize].speed = cap[2].parse::<f32>().unwrap();
+            }
+            28 => {
+                let re = Regex::new(r"group (rr|ar|lar|usp|az) scram (on|off)").unwrap();
+                let cap = re.captures(command).unwrap();
+                let group = RodGroup::from_name(&cap[1]).unwrap();
+                mainstruct.core.rod_drives[group as usize].scram = &cap[2] == "on";
+            }
+            29 => {
+                if command.ends_with("on") {
+                    enable_regulator(mainstruct);
+                } else {
+                    disable_regulator(mainstruct);
+                }
+            }
+            30 => {
+                let re = Regex::new(r"auto power (\d+(?:\.\d+)?)").unwrap();
+                let cap = re.captures(command).unwrap();
+                let power = cap[1].parse::<f32>().unwrap();
+                if power <= MAX_SETPOINT {
+                    mainstruct.core.regulator.setpoint = power;
+                } else {
+                    mainstruct.data.log.insert(
+                        0,
+                        format!("Power setpoint must be at most {} %", MAX_SETPOINT),
+                    );
+                }
+            }
+            31 => {
+                if command.ends_with("on") {
+                    enable_local_regulation(mainstruct);
+                } else {
+                    disable_local_regulation(mainstruct);
+                }
+            }
+            32 => {
+                mainstruct.clock.paused = true;
+            }
+            33 => {
+                mainstruct.clock.paused = false;
+            }
+            34 => {
+                // single steps of dt, run while paused as well
+                let re = Regex::new(r"^step (\d+)$").unwrap();
+                let cap = re.captures(command).unwrap();
+                let requested = cap[1].parse::<u32>().unwrap_or(u32::MAX);
+                let clock = &mut mainstruct.clock;
+                let pending = clock.pending_steps.saturating_add(requested);
+                clock.pending_steps = pending.min(MAX_PENDING_STEPS);
+                if pending > MAX_PENDING_STEPS {
+                    let message = format!("Steps queued capped at {}", MAX_PENDING_STEPS);
+                    mainstruct.data.log.insert(0, message);
+                }
+            }
+            35 => {
+                let re = Regex::new(r"^speed (\d+(?:\.\d+)?)x?$").unwrap();
+                let cap = re.captures(command).unwrap();
+                let speed = cap[1].parse::<f32>().unwrap();
+                if speed > 0.0 && speed <= MAX_SPEED {
+                    mainstruct.clock.speed = speed;
+                } else {
+                    mainstruct.data.log.insert(
+                        0,
+                        format!("Speed must be above 0 and at most {}x", MAX_SPEED),
+                    );
+                }
+            }
+            36 => {
+                let re = Regex::new(r"^save ([\w-]+)$").unwrap();
+                let cap = re.captures(command).unwrap();
+                let message = match save_snapshot(mainstruct, &cap[1]) {
+                    Ok(path) => format!("Saved {}", path.display()),
+                    Err(error) => format!("Cannot save: {}", error),
+                };
+                mainstruct.data.log.insert(0, message);
+            }
+            37 => {
+                // everything is replaced, the log and trends included
+                let re = Regex::new(r"^load ([\w-]+)$").unwrap();
+                let cap = re.captures(command).unwrap();
+                match load_snapshot(&cap[1]) {
+                    Ok(state) => {
+                        // a recording or replay runs on through the load
+                        let session = std::mem::take(&mut mainstruct.session);
+                        *mainstruct = state;
+                        mainstruct.session = session;
+                        mainstruct.data.log.insert(0, format!("Loaded {}", &cap[1]));
+                    }
+                    Err(error) => {
+                        mainstruct
+                            .data
+                            .log
+                            .insert(0, format!("Cannot load: {}", error));
+                    }
+                }
+            }
+            38 => {
+                list_objectives(mainstruct);
+            }
+            39 => {
+                list_malfunctions(mainstruct);
+            }
+            40 => {
+                let re = Regex::new(r"^repair (\d+|all)$").unwrap();
+                let cap = re.captures(command).unwrap();
+                if let Err(error) = clear_malfunction(mainstruct, cap[1].parse::<usize>().ok()) {
+                    mainstruct
+                        .data
+                        .log
+                        .insert(0, format!("Cannot repair: {}", error));
+                }
+            }
+            41 => {
+                let re = Regex::new(r"^malfunction (.+)$").unwrap();
+                let cap = re.captures(command).unwrap();
+                let result = match parse_malfunction(&cap[1], mainstruct.clock.time) {
+                    Some((failure, trigger)) => arm_malfunction(mainstruct, failure, trigger),
+                    None => Err(format!("unknown malfunction {}", &cap[1])),
+                };
+                if let Err(error) = result {
+                    mainstruct
+                        .data
+                        .log
+                        .insert(0, format!("Cannot arm: {}", error));
+                }
+            }
+            42 => {
+                let re = Regex::new(r"^level(?: (\d+))?$").unwrap();
+                let cap = re.captures(command).unwrap();
+                let result = match cap.get(1) {
+                    Some(level) => match level.as_str().parse::<u8>() {
+                        Ok(level) => set_level(mainstruct, level),
+                        Err(_) => Err(format!("there is no level {}", level.as_str())),
+                    },
+                    None => {
+                        let message = format!("Simulation level {}", mainstruct.level);
+                        mainstruct.data.log.insert(0, message);
+                        Ok(())
+                    }
+                };
+                if let Err(error) = result {
+                    mainstruct
+                        .data
+                        .log
+                        .insert(0, format!("Cannot set level: {}", error));
+                }
+            }
 
             _ => {
                 //println!("no match");
@@ -240,3 +547,50 @@ pub fn send_command(command: &str, mainstruct: &mut MainStruct, height: u16) {
         }
     }
 }
+
+/// The group named at the end of a group command, None for every rod.
+fn command_group(command: &str) -> Option<RodGroup> {
+    let re = Regex::new(r"\b(rr|ar|lar|usp|az)$").unwrap();
+    re.captures(command)
+        .and_then(|cap| RodGroup::from_name(&cap[1]))
+}
+
+fn in_group(rod: &FuelRod, group: Option<RodGroup>) -> bool {
+    match group {
+        Some(group) => rod.cell_type.rod_group() == Some(group),
+        None => rod.cell_type.is_rod(),
+    }
+}
+
+/// The cell of a rod from its number on the map, logging why when there is no rod there
+/// or when its group only takes group commands.
+fn rod_cell(mainstruct: &mut MainStruct, number: usize) -> Option<(usize, usize)> {
+    match cell_position(mainstruct, number) {
+        Some((row, col)) => match mainstruct.absorber_rods[row][col].cell_type.rod_group() {
+            Some(group) if mainstruct.core.rod_drives[group as usize].individual => {
+                Some((row, col))
+            }
+            Some(group) => {
+                mainstruct
+                    .data
+                    .log
+                    .insert(0, format!("{} rods only take group commands", group.name()));
+                None
+            }
+            None => {
+                mainstruct
+                    .data
+                    .log
+                    .insert(0, format!("Cell {} has no control rod", number));
+                None
+            }
+        },
+        None => {
+            mainstruct
+                .data
+                .log
+                .insert(0, format!("There is no cell {}", number));
+            None
+        }
+    }
+}
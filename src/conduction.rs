@@ -0,0 +1,89 @@
+use crate::geometry::RODS_PER_CHANNEL;
+use crate::structs::{AxialNode, FuelRodData};
+
+// conductivities in W/cm/K, conductances in W/cm^2/K
+const FUEL_CONDUCTIVITY: f32 = 0.03; // UO2 at operating temperature
+const GAP_CONDUCTANCE: f32 = 0.5; // helium filled pellet to cladding gap
+const CLADDING_CONDUCTIVITY: f32 = 0.17; // zirconium niobium alloy
+const BOILING_HEAT_TRANSFER: f32 = 3.0; // nucleate boiling at nominal flow
+
+// limits shown on the core map, in C
+pub const CENTERLINE_LIMIT: f32 = 2800.0; // UO2 melting
+pub const CLADDING_LIMIT: f32 = 700.0; // onset of fast cladding oxidation and ballooning
+
+/// Thermal resistances per unit length of a fuel rod in K cm/W, from the coolant inwards.
+pub struct ThermalResistances {
+    pub coolant: f32,
+    pub cladding: f32,
+    pub gap: f32,
+    pub pellet: f32,
+}
+
+impl ThermalResistances {
+    /// `heat_removal` scales the coolant film, 1 at nominal flow.
+    pub fn new(fuel_rod_data: &FuelRodData, heat_removal: f32) -> Self {
+        let pellet_diameter = fuel_rod_data.fuel_pellet.diameter;
+        let inner_diameter = fuel_rod_data.cladding.inner_diameter;
+        let outer_diameter = inner_diameter + 2.0 * fuel_rod_data.cladding.thickness;
+        Self {
+            coolant: 1.0
+                / (std::f32::consts::PI * outer_diameter * BOILING_HEAT_TRANSFER * heat_removal),
+            cladding: (outer_diameter / inner_diameter).ln()
+                / (2.0 * std::f32::consts::PI * CLADDING_CONDUCTIVITY),
+            gap: 1.0 / (std::f32::consts::PI * pellet_diameter * GAP_CONDUCTANCE),
+            pellet: 1.0 / (4.0 * std::f32::consts::PI * FUEL_CONDUCTIVITY),
+        }
+    }
+
+    /// From the coolant to the volume average of the pellet, which sees half the pellet drop.
+    pub fn to_average(&self) -> f32 {
+        self.coolant + self.cladding + self.gap + self.pellet / 2.0
+    }
+}
+
+/// Linear heat rate of the fuel rods of one channel at a node, in W/cm.
+/// `channel_power` is in MW and `stack_length` is the length of one assembly in m.
+pub fn linear_heat_rate(channel_power: f32, node_flux: f32, stack_length: f32) -> f32 {
+    channel_power * 1.0e6 * node_flux / (RODS_PER_CHANNEL * stack_length * 100.0)
+}
+
+/// Fill in the radial profile of a node from its pellet average temperature.
+/// The pellet stores the heat, so the rest of the profile follows the heat flowing out of it.
+pub fn radial_temperatures(
+    node: &mut AxialNode,
+    coolant_temperature: f32,
+    resistances: &ThermalResistances,
+) {
+    let heat = (node.fuel_temperature - coolant_temperature) / resistances.to_average();
+    node.cladding_outer_temperature = coolant_temperature + heat * resistances.coolant;
+    node.cladding_inner_temperature = node.cladding_outer_temperature + heat * resistances.cladding;
+    node.surface_temperature = node.cladding_inner_temperature + heat * resistances.gap;
+    node.centerline_temperature = node.surface_temperature + heat * resistances.pellet;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::PhysicalVariables;
+
+    #[test]
+    fn test_nominal_profile() {
+        // an average rod at full power runs near 800 C on the centerline
+        // with the cladding only a few tens of degrees over the coolant
+        let fuel_rod_data = PhysicalVariables::default().fuel_rod_data;
+        let resistances = ThermalResistances::new(&fuel_rod_data, 1.0);
+        let heat = linear_heat_rate(3200.0 / 1661.0, 1.0, fuel_rod_data.fuel_pellet.stack_length);
+        assert!(heat > 120.0 && heat < 180.0, "heat: {}", heat);
+
+        let mut node = AxialNode {
+            fuel_temperature: 284.0 + heat * resistances.to_average(),
+            ..Default::default()
+        };
+        radial_temperatures(&mut node, 284.0, &resistances);
+        assert!(node.centerline_temperature > node.surface_temperature);
+        assert!(node.surface_temperature > node.cladding_inner_temperature);
+        assert!(node.cladding_inner_temperature > node.cladding_outer_temperature);
+        assert!(node.centerline_temperature > 700.0 && node.centerline_temperature < 900.0);
+        assert!(node.cladding_inner_temperature < 350.0);
+    }
+}
@@ -0,0 +1,93 @@
+use rayon::prelude::*;
+
+use crate::kinetics::NOMINAL_THERMAL_POWER;
+use crate::level::{enabled, Subsystem};
+use crate::structs::{MainStruct, DECAY_HEAT_GROUPS};
+
+pub fn decay_heat(mainstruct: &mut MainStruct, dt: f32) {
+    // the part of the fission energy that only comes out later through decay
+    let delayed_fraction: f64 = DECAY_HEAT_GROUPS.iter().map(|group| group.0).sum();
+    // below its level all of the fission energy comes out at once, and stops with the chain reaction
+    let simulated = enabled(mainstruct, Subsystem::DecayHeat);
+
+    mainstruct.absorber_rods.par_iter_mut().for_each(|row| {
+        row.par_iter_mut().for_each(|rod| {
+            // each group relaxes exactly towards its share of the present fission power
+            let fission_power = rod.fission_power as f64;
+            if !simulated {
+                rod.decay_groups.iter_mut().for_each(|group| *group = 0.0);
+                rod.decay_heat = 0.0;
+                rod.thermal_power_output = rod.fission_power;
+                return;
+            }
+            for (group, (fraction, decay_constant)) in
+                rod.decay_groups.iter_mut().zip(DECAY_HEAT_GROUPS.iter())
+            {
+                let equilibrium = fraction * fission_power;
+                *group = equilibrium + (*group - equilibrium) * (-decay_constant * dt as f64).exp();
+            }
+            let decay_heat: f64 = rod.decay_groups.iter().sum();
+            rod.decay_heat = decay_heat as f32;
+            rod.thermal_power_output =
+                (fission_power * (1.0 - delayed_fraction) + decay_heat) as f32;
+        });
+    });
+
+    // the rows are added up in order, so the sum comes out the same on every run
+    let (decay_heat, thermal_power) = mainstruct
+        .absorber_rods
+        .par_iter()
+        .map(|row| {
+            row.iter().fold((0.0, 0.0), |acc, rod| {
+                (acc.0 + rod.decay_heat, acc.1 + rod.thermal_power_output)
+            })
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .fold((0.0, 0.0), |a, b| (a.0 + b.0, a.1 + b.1));
+    mainstruct.core.decay_heat = decay_heat / NOMINAL_THERMAL_POWER * 100.0;
+    mainstruct.core.thermal_power = thermal_power / NOMINAL_THERMAL_POWER * 100.0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::fuel_channels;
+
+    fn set_fission_power(mainstruct: &mut MainStruct, power: f32) {
+        for row in mainstruct.absorber_rods.iter_mut() {
+            for rod in row.iter_mut() {
+                if rod.cell_type.is_fuel() {
+                    rod.fission_power = power;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_shutdown_curve() {
+        // after long operation the decay heat falls to roughly 6%, 3% and 1% of full power
+        // one second, a hundred seconds and three hours after shutdown
+        let mut mainstruct = MainStruct::default();
+        let channels = fuel_channels(&mainstruct) as f32;
+        set_fission_power(&mut mainstruct, NOMINAL_THERMAL_POWER / channels);
+        for _ in 0..1000 {
+            decay_heat(&mut mainstruct, 1.0e5);
+        }
+        assert!((mainstruct.core.thermal_power - 100.0).abs() < 0.1);
+
+        set_fission_power(&mut mainstruct, 0.0);
+        let mut time = 0.0;
+        for (elapsed, expected) in [(1.0, 6.0), (100.0, 3.2), (10800.0, 1.0)] {
+            decay_heat(&mut mainstruct, elapsed - time);
+            time = elapsed;
+            let power = mainstruct.core.thermal_power;
+            assert!(
+                (power - expected).abs() < expected * 0.15,
+                "{}: {}",
+                elapsed,
+                power
+            );
+        }
+    }
+}
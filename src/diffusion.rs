@@ -0,0 +1,243 @@
+use crate::axial::axial_flux;
+use crate::geometry::CORE_HEIGHT;
+use crate::kinetics::flux_weighted;
+use crate::structs::MainStruct;
+
+// two group constants of a fuel cell of the RBMK lattice, lengths in cm,
+// the fission terms are nu times the fission cross section
+const FAST_DIFFUSION: f64 = 1.3;
+const FAST_ABSORPTION: f64 = 0.0006;
+const SLOWING_DOWN: f64 = 0.0035;
+const FAST_FISSION: f64 = 0.0003;
+const THERMAL_DIFFUSION: f64 = 0.9;
+const THERMAL_ABSORPTION: f64 = 0.0035;
+const THERMAL_FISSION: f64 = 0.00424;
+// graphite columns without fuel, the rod and detector channels and the reflector
+const GRAPHITE_ABSORPTION: f64 = 0.0004;
+// linear extrapolation distance past the edge of the core, in diffusion lengths
+const EXTRAPOLATION: f64 = 2.13;
+// outer iterations from a flat flux, and on each later step, where the flux carries over
+// and only has to follow what changed since the last one
+const MAX_OUTER_ITERATIONS: usize = 200;
+const STEP_OUTER_ITERATIONS: usize = 20;
+const INNER_SWEEPS: usize = 3;
+const TOLERANCE: f64 = 1.0e-7;
+
+pub fn diffusion(mainstruct: &mut MainStruct) {
+    let height = mainstruct.absorber_rods.len();
+    let width = mainstruct.absorber_rods[0].len();
+
+    // every effect on a cell shows up as a change of its thermal absorption,
+    // the axial solve folds the nodes of each channel into one reactivity
+    axial_flux(mainstruct);
+
+    let solver = &mut mainstruct.core.diffusion;
+    let mut outer_iterations = STEP_OUTER_ITERATIONS;
+    if solver.thermal_flux.len() != height || solver.thermal_flux[0].len() != width {
+        solver.fast_flux = vec![vec![1.0; width]; height];
+        solver.thermal_flux = vec![vec![1.0; width]; height];
+        solver.eigenvalue = 1.0;
+        outer_iterations = MAX_OUTER_ITERATIONS;
+    }
+    let fuel: Vec<Vec<bool>> = mainstruct
+        .absorber_rods
+        .iter()
+        .map(|row| row.iter().map(|rod| rod.cell_type.is_fuel()).collect())
+        .collect();
+    let absorption: Vec<Vec<f64>> = mainstruct
+        .absorber_rods
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|rod| {
+                    if rod.cell_type.is_fuel() {
+                        THERMAL_ABSORPTION * (1.0 - rod.reactivity as f64).max(0.05)
+                    } else {
+                        GRAPHITE_ABSORPTION
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    let pitch = mainstruct.core.lattice_pitch as f64;
+    let axial_buckling = (std::f64::consts::PI / (CORE_HEIGHT * 100.0)).powi(2);
+    let fast = Group {
+        diffusion: FAST_DIFFUSION,
+        pitch,
+        axial_buckling,
+    };
+    let thermal = Group {
+        diffusion: THERMAL_DIFFUSION,
+        pitch,
+        axial_buckling,
+    };
+
+    let mut eigenvalue = solver.eigenvalue;
+    let mut source = fission_source(&solver.fast_flux, &solver.thermal_flux, &fuel);
+    for _ in 0..outer_iterations {
+        for _ in 0..INNER_SWEEPS {
+            fast.sweep(&mut solver.fast_flux, |i, j| {
+                (FAST_ABSORPTION + SLOWING_DOWN, source[i][j] / eigenvalue)
+            });
+        }
+        let fast_flux = &solver.fast_flux;
+        for _ in 0..INNER_SWEEPS {
+            thermal.sweep(&mut solver.thermal_flux, |i, j| {
+                (absorption[i][j], SLOWING_DOWN * fast_flux[i][j])
+            });
+        }
+        let new_source = fission_source(&solver.fast_flux, &solver.thermal_flux, &fuel);
+        let old_total: f64 = source.iter().flatten().sum();
+        let new_total: f64 = new_source.iter().flatten().sum();
+        let new_eigenvalue = eigenvalue * new_total / old_total;
+        source = new_source;
+        let converged = (new_eigenvalue - eigenvalue).abs() < TOLERANCE;
+        eigenvalue = new_eigenvalue;
+        if converged {
+            break;
+        }
+    }
+    solver.eigenvalue = eigenvalue;
+
+    normalize(&mut solver.fast_flux, &fuel);
+    normalize(&mut solver.thermal_flux, &fuel);
+    solver.power_shape = fission_source(&solver.fast_flux, &solver.thermal_flux, &fuel);
+    normalize(&mut solver.power_shape, &fuel);
+
+    // whatever the feedback terms do not explain is the lattice excess less leakage
+    let rods = flux_weighted(mainstruct, |rod, k| rod.nodes[k].rod_reactivity as f64);
+    let reactivity = &mut mainstruct.core.kinetics.reactivity;
+    reactivity.rods = rods;
+    reactivity.lattice = 0.0;
+    reactivity.lattice = (eigenvalue - 1.0) / eigenvalue - reactivity.total();
+}
+
+struct Group {
+    diffusion: f64,
+    pitch: f64,
+    axial_buckling: f64,
+}
+
+impl Group {
+    /// One Gauss-Seidel sweep of the finite difference balance of a group.
+    /// `cell` gives the removal cross section and the source of each cell.
+    fn sweep<F>(&self, flux: &mut [Vec<f64>], cell: F)
+    where
+        F: Fn(usize, usize) -> (f64, f64),
+    {
+        let height = flux.len();
+        let width = flux[0].len();
+        let coupling = self.diffusion / (self.pitch * self.pitch);
+        // leakage through a face on the edge of the core, to zero flux at the extrapolated boundary
+        let edge =
+            self.diffusion / (self.pitch * (self.pitch / 2.0 + EXTRAPOLATION * self.diffusion));
+        for i in 0..height {
+            for j in 0..width {
+                let (removal, source) = cell(i, j);
+                let mut diagonal = removal + self.diffusion * self.axial_buckling;
+                let mut neighbors = 0.0;
+                for (di, dj) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    let (ni, nj) = (i as i32 + di, j as i32 + dj);
+                    if ni < 0 || nj < 0 || ni >= height as i32 || nj >= width as i32 {
+                        diagonal += edge;
+                    } else {
+                        diagonal += coupling;
+                        neighbors += coupling * flux[ni as usize][nj as usize];
+                    }
+                }
+                flux[i][j] = (source + neighbors) / diagonal;
+            }
+        }
+    }
+}
+
+/// Fission neutrons born in each cell, only the fuel channels have any.
+fn fission_source(
+    fast_flux: &[Vec<f64>],
+    thermal_flux: &[Vec<f64>],
+    fuel: &[Vec<bool>],
+) -> Vec<Vec<f64>> {
+    fast_flux
+        .iter()
+        .zip(thermal_flux.iter())
+        .zip(fuel.iter())
+        .map(|((fast, thermal), fuel)| {
+            fast.iter()
+                .zip(thermal.iter())
+                .zip(fuel.iter())
+                .map(|((fast, thermal), fuel)| {
+                    if *fuel {
+                        FAST_FISSION * fast + THERMAL_FISSION * thermal
+                    } else {
+                        0.0
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Scale to an average of 1 over the fuel channels.
+fn normalize(values: &mut [Vec<f64>], fuel: &[Vec<bool>]) {
+    let channels = fuel.iter().flatten().filter(|fuel| **fuel).count() as f64;
+    let total: f64 = values
+        .iter()
+        .flatten()
+        .zip(fuel.iter().flatten())
+        .filter(|(_, fuel)| **fuel)
+        .map(|(value, _)| value)
+        .sum();
+    let average = total / channels;
+    if average > 0.0 {
+        for value in values.iter_mut().flatten() {
+            *value /= average;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_core_excess() {
+        let mut mainstruct = MainStruct::default();
+        for row in mainstruct.absorber_rods.iter_mut() {
+            for rod in row.iter_mut() {
+                rod.absorber_rod_position = 0.0;
+            }
+        }
+        diffusion(&mut mainstruct);
+        let rho = mainstruct.core.kinetics.reactivity.total();
+        assert!(rho > 0.05 && rho < 0.07, "rho: {}", rho);
+    }
+
+    fn settle(mainstruct: &mut MainStruct) {
+        for _ in 0..MAX_OUTER_ITERATIONS / STEP_OUTER_ITERATIONS {
+            diffusion(mainstruct);
+        }
+    }
+
+    #[test]
+    fn test_local_rod_tilts_flux() {
+        // withdrawing one rod on the edge raises the flux around it far more than across the core
+        let mut mainstruct = MainStruct::default();
+        for row in mainstruct.absorber_rods.iter_mut() {
+            for rod in row.iter_mut() {
+                rod.absorber_rod_position = 100.0;
+            }
+        }
+        // each step only runs part of the outer iterations, so let the flux settle over as many
+        // steps as a solve from a flat flux takes
+        settle(&mut mainstruct);
+        let before = mainstruct.core.diffusion.thermal_flux.clone();
+        assert!(mainstruct.absorber_rods[22][1].cell_type.is_rod());
+        mainstruct.absorber_rods[22][1].absorber_rod_position = 0.0;
+        settle(&mut mainstruct);
+        let after = &mainstruct.core.diffusion.thermal_flux;
+        let near = after[22][2] / before[22][2];
+        let opposite = after[25][45] / before[25][45];
+        assert!(near > 1.1 && opposite < 1.0, "{} {}", near, opposite);
+    }
+}
@@ -1,6 +1,7 @@
-use crate::arcfm::{fuel_rod_svg, temperature, SvgPoints};
+use crate::arcfm::{fuel_rod_svg, SvgPoints};
 use crate::svg::render_svg;
-use crate::{arcfm::fuel_rod_table, structs::MainStruct};
+use crate::arcfm::fuel_rod_table;
+use rbmk::structs::MainStruct;
 use regex::Regex;
 use tui::style::Modifier;
 use tui::widgets::{List, ListItem, ListState};
@@ -78,9 +79,8 @@ pub fn draw(
         frame.render_widget(left_tabs, chunks_3[0]);
 
         //frame.render_widget(reactor_core, chunks_3[0]);
-        temperature(mainstruct, 5, 5);
         match mainstruct.data.left_tab_index {
-            0 => fuel_rod_table(5, 5, chunks_3[0], frame, mainstruct),
+            0 => fuel_rod_table(chunks_3[0], frame, mainstruct),
             1 => fuel_rod_svg(mainstruct, frame, chunks_3[0]),
             2 => draw_turbine(mainstruct, frame, chunks_3[0]),
             3 => checklist(mainstruct, frame, chunks_3[0]),
@@ -223,27 +223,28 @@ fn checklist(
 
     
     let selected = mainstruct.data.checklist_selected - 1;
-    let mut list_vec: Vec<ListItem> = Vec::new();
+    let mut names: Vec<String> = Vec::new();
 
     //mainstruct.data.items[selected].0 =  mainstruct.data.items[selected].clone().0.style(Style::default().fg(Color::Yellow));
     for (item, children, state) in mainstruct.data.items.clone().into_iter() {
-        list_vec.push(item);
+        names.push(item);
         if state {
             for child in children {
-                list_vec.push(child);
+                names.push(child);
             }
         }
         
     }
-    mainstruct.data.checklist_length = list_vec.len();
-    mainstruct.data.selected_item = list_vec[selected].clone();
-    for (i, item) in list_vec.iter_mut().enumerate() {
+    mainstruct.data.checklist_length = names.len();
+    mainstruct.data.selected_item = names[selected].clone();
+    let mut list_vec: Vec<ListItem> = Vec::new();
+    for (i, name) in names.into_iter().enumerate() {
         let color = if i == selected {
             Color::Yellow
         } else {
             Color::White
         };
-        *item = item.clone().style(Style::default().fg(color));
+        list_vec.push(ListItem::new(name).style(Style::default().fg(color)));
     }
     
     let list = List::new(list_vec).highlight_style(
@@ -0,0 +1,7 @@
+// height of the core in m, the rod travel and the axial nodes both span it
+pub const CORE_HEIGHT: f64 = 7.0;
+// fuel rods side by side in an assembly
+pub const RODS_PER_ASSEMBLY: f32 = 18.0;
+// assemblies stacked one above the other in each channel
+pub const ASSEMBLIES_PER_CHANNEL: f32 = 2.0;
+pub const RODS_PER_CHANNEL: f32 = RODS_PER_ASSEMBLY * ASSEMBLIES_PER_CHANNEL;
@@ -1,134 +1,151 @@
 use rayon::prelude::*;
 
-use crate::structs::MainStruct;
+use crate::axial::node_average;
+use crate::conduction::{linear_heat_rate, radial_temperatures, ThermalResistances};
+use crate::kinetics::flux_weighted;
+use crate::malfunction::{failed_drives, rod_mobility};
+use crate::steam::saturation_temperature;
+use crate::structs::{MainStruct, DELAYED_GROUPS};
 use rand::seq::SliceRandom;
 
-pub fn interpolate_position(mainstruct: &mut MainStruct) {
-    mainstruct.absorber_rods.par_iter_mut().for_each(|row| {
-        row.par_iter_mut().for_each(|rod| match rod.insert_rod {
-            true => {
-                if rod.absorber_rod_position < rod.set_point {
-                    rod.absorber_rod_position += mainstruct.core.speed_setpoint;
-                } else if rod.absorber_rod_position
-                    > (rod.set_point + mainstruct.core.speed_setpoint)
-                {
-                    rod.absorber_rod_position -= mainstruct.core.speed_setpoint;
-                } else {
-                    rod.absorber_rod_position = rod.set_point;
+pub fn interpolate_position(mainstruct: &mut MainStruct, dt: f32) {
+    // on a scram the drives are declutched and the rods run in at the full servo speed
+    const SCRAM_SPEED: f32 = 5.7; // 0.4 m/s over the 7 m of travel, in %/s
+
+    let drives = mainstruct.core.rod_drives;
+    let scram = mainstruct.core.scram;
+    let mobility = rod_mobility(mainstruct);
+    let failed = failed_drives(mainstruct);
+    mainstruct
+        .absorber_rods
+        .par_iter_mut()
+        .enumerate()
+        .for_each(|(i, row)| {
+            row.par_iter_mut().enumerate().for_each(|(j, rod)| {
+                let Some(group) = rod.cell_type.rod_group() else {
+                    return;
+                };
+                let drive = drives[group as usize];
+                let mobility = mobility
+                    .iter()
+                    .filter(|(cell, _)| *cell == (i, j))
+                    .map(|(_, mobility)| *mobility)
+                    .fold(1.0, f32::min);
+                if scram && drive.scram {
+                    rod.absorber_rod_position = (rod.absorber_rod_position
+                        + drive.speed.max(SCRAM_SPEED) * mobility * dt)
+                        .min(100.0);
+                    return;
                 }
-            }
-            false => {
-                if rod.absorber_rod_position < 100.0 {
-                    rod.absorber_rod_position += mainstruct.core.speed_setpoint;
-                } else {
-                    rod.absorber_rod_position = 100.0;
+                // a failed drive holds its rods, they still drop on a scram
+                if failed[group as usize] {
+                    return;
                 }
-            }
+                let speed = drive.speed * mobility * dt;
+                match rod.insert_rod {
+                    // rods that take no set point hold where they are until driven in
+                    true if !group.takes_set_point() => {}
+                    true => {
+                        if rod.absorber_rod_position < rod.set_point {
+                            rod.absorber_rod_position += speed;
+                        } else if rod.absorber_rod_position > (rod.set_point + speed) {
+                            rod.absorber_rod_position -= speed;
+                        } else {
+                            rod.absorber_rod_position = rod.set_point;
+                        }
+                    }
+                    false => {
+                        if rod.absorber_rod_position < 100.0 {
+                            rod.absorber_rod_position += speed;
+                        } else {
+                            rod.absorber_rod_position = 100.0;
+                        }
+                    }
+                }
+            });
         });
-    });
 }
 
-pub fn graphline(mainstruct: &mut MainStruct) {
+pub fn graphline(mainstruct: &mut MainStruct, dt: f32) {
     //println!("In graph line method");
     //mainstruct.data.log.push(format!("Graph newest: {:?}, pos: {}", mainstruct.data.graphs[3][0], mainstruct.absorber_rods[0][0].absorber_rod_position));
     for graph in mainstruct.data.graphs.iter_mut() {
         graph.truncate(30);
         for point in graph.iter_mut() {
-            point.1 += 1.0;
+            point.1 += dt as f64;
         }
     }
 
     let vs: Vec<f64> = vec![1.0, 2.0, 3.0];
 
     mainstruct.data.graphs[0].insert(0, (mainstruct.core.thermal_power as f64, 0.0));
-    mainstruct.data.graphs[1].insert(0, (*vs.choose(&mut rand::thread_rng()).unwrap(), 0.0));
-    mainstruct.data.graphs[2].insert(0, (mainstruct.data.neutron_rate as f64, 0.0));
+    mainstruct.data.graphs[1].insert(0, (*vs.choose(&mut mainstruct.rng).unwrap(), 0.0));
+    // ten per decade of count rate, so the source range fits the chart
+    mainstruct.data.graphs[2].insert(0, (mainstruct.data.neutron_rate as f64 * 10.0, 0.0));
     mainstruct.data.graphs[3].insert(0, (mainstruct.data.neutron_flux as f64, 0.0));
+    // reactivity in cents, one dollar is prompt critical
+    let beta: f64 = DELAYED_GROUPS.iter().map(|group| group.0).sum();
+    mainstruct.data.graphs[4].insert(0, (mainstruct.data.reactivity as f64 / beta * 100.0, 0.0));
 }
-pub fn neutron_rate(mainstruct: &mut MainStruct) {
-    mainstruct.absorber_rods.par_iter_mut().for_each(|row| {
-        row.par_iter_mut().for_each(|rod| {
-            const MAX: f32 = 15.0; // 100% neutron flux rate
-            const MIN: f32 = -15.0; // 0% neutron flux rate
-            rod.neutron_rate =
-                (MAX - MIN) * (1.0 - rod.absorber_rod_position / 100.0) + MIN;
-        });
-    });
-    //mainstruct.data.log.push(format!("Neutron rate: {}", mainstruct.data.neutron_rate));
-    mainstruct.data.neutron_rate = mainstruct
-        .absorber_rods
-        .par_iter()
-        .map(|row| {
-            row.par_iter().map(|rod| rod.neutron_rate).sum::<f32>() / (mainstruct.core.width) as f32
-        })
-        .sum::<f32>()
-        / mainstruct.core.height as f32;
-    //mainstruct.data.log.push(format!("Reactivity: {}, y value: {}", mainstruct.data.reactivity, mainstruct.data.graphs[2][0].1));
-}
-pub fn neutron_flux(mainstruct: &mut MainStruct) {
-    const MAX: f32 = 110.0; // 100% neutron flux rate
-    const MIN: f32 = 0.0; // 0% neutron flux rate
-
-    let average_rod_position = mainstruct
-        .absorber_rods
-        .par_iter()
-        .map(|row| {
-            row.par_iter()
-                .map(|rod| rod.absorber_rod_position)
-                .sum::<f32>()
-        })
-        .sum::<f32>()
-        / (mainstruct.core.width * mainstruct.core.height) as f32;
-    //let k = 2.5E14; // proportionality constant
-    //mainstruct.data.log.push(format!("average rod position: {}", average_rod_position));
+pub fn fuel_temperature(mainstruct: &mut MainStruct, dt: f32) {
+    const TIME_CONSTANT: f32 = 5.0; // fuel heat capacity over heat removal in s
 
-    let normalized_flux = match average_rod_position as i32 {
-        0..=100 => (MAX - MIN) * (1.0 - average_rod_position / 100.0) + MIN,
-        _ => 0.0, // handle invalid input
-    };
-
-    //mainstruct.data.log.push(format!("normalized flux: {}", normalized_flux));
-    // normalized neutron flux should be between 0 and 110
-    mainstruct.data.neutron_flux = normalized_flux;
-}
-pub fn fuel_temperature(mainstruct: &mut MainStruct) {
-    const MAX: f32 = 600.0; // 100% neutron flux rate
-    const MIN: f32 = 0.0; // 0% neutron flux rate
+    // heat removal drops off somewhat without forced circulation
+    let flow = (mainstruct.core.coolant_flow / 100.0).clamp(0.0, 1.2);
+    let heat_removal = 0.6 + 0.4 * flow;
+    let coolant_temperature = saturation_temperature(mainstruct.core.steam.steam_pressure);
+    let lag = 1.0 - (-dt * heat_removal / TIME_CONSTANT).exp();
+    let fuel_rod_data = &mainstruct.physical_variables.fuel_rod_data;
+    let resistances = ThermalResistances::new(fuel_rod_data, heat_removal);
+    let stack_length = fuel_rod_data.fuel_pellet.stack_length;
 
     mainstruct.absorber_rods.par_iter_mut().for_each(|row| {
         row.par_iter_mut().for_each(|rod| {
-            rod.fuel_temperature =
-                (MAX - MIN) * (1.0 - rod.absorber_rod_position / 100.0) + MIN;
+            if !rod.cell_type.is_fuel() {
+                return;
+            }
+            // lumped fuel heated by the node power and cooled towards the coolant,
+            // fission and decay heat, so the fuel stays warm after a shutdown
+            for node in rod.nodes.iter_mut() {
+                let heat = linear_heat_rate(rod.thermal_power_output, node.flux, stack_length);
+                let equilibrium = coolant_temperature + heat * resistances.to_average();
+                node.fuel_temperature += (equilibrium - node.fuel_temperature) * lag;
+                radial_temperatures(node, coolant_temperature, &resistances);
+            }
+            rod.fuel_temperature = node_average(rod, |node| node.fuel_temperature);
+            rod.centerline_temperature = rod
+                .nodes
+                .iter()
+                .map(|node| node.centerline_temperature)
+                .fold(f32::MIN, f32::max);
+            rod.cladding_temperature = rod
+                .nodes
+                .iter()
+                .map(|node| node.cladding_inner_temperature)
+                .fold(f32::MIN, f32::max);
         });
     });
-    let thermal_power = mainstruct
-        .absorber_rods
-        .par_iter()
-        .map(|row| {
-            row.par_iter().map(|rod| rod.fuel_temperature).sum::<f32>()
-                / mainstruct.core.width as f32
-        })
-        .sum::<f32>()
-        / mainstruct.core.height as f32;
-
-    const TH_MAX: f32 = 110.0;
-    const TH_MIN: f32 = 0.0;
-    // 110% is FP
-    let normalized_thermal_power =
-        (TH_MAX - TH_MIN) * (thermal_power / 600.0) + TH_MIN;
-    //mainstruct.data.log.push(format!("Thermal power: {}", normalized_thermal_power));
-    mainstruct.core.thermal_power = normalized_thermal_power;
 
+    let coefficient = mainstruct.core.doppler_coefficient;
+    mainstruct.core.kinetics.reactivity.doppler =
+        flux_weighted(mainstruct, |rod, k| {
+            doppler_worth(coefficient, rod.nodes[k].fuel_temperature)
+        });
     //mainstruct.data.log.push(format!("Fuel temperature: {}", mainstruct.absorber_rods[0][0].fuel_temperature));
 }
-pub fn turbine(mainstruct: &mut MainStruct) {
+/// Doppler reactivity of a channel in dk/k, zero for cold fuel.
+pub fn doppler_worth(doppler_coefficient: f32, fuel_temperature: f32) -> f64 {
+    const REFERENCE_TEMPERATURE: f32 = 20.0;
+    (doppler_coefficient * (fuel_temperature - REFERENCE_TEMPERATURE)) as f64
+}
+pub fn turbine(mainstruct: &mut MainStruct, dt: f32) {
     let speeds = [0.0, 900.0, 1800.0, 2700.0, 3600.0];
     let speed_index = mainstruct.turbine.speed_setpoint_step;
     mainstruct.turbine.setpoint_speed = speeds[speed_index as usize];
     if mainstruct.turbine.turbine_speed < mainstruct.turbine.setpoint_speed {
-        mainstruct.turbine.turbine_speed += 1.0 * mainstruct.turbine.steam_flow_rate;
-    } else if mainstruct.turbine.turbine_speed > (mainstruct.turbine.setpoint_speed + 1.0) {
-        mainstruct.turbine.turbine_speed -= 1.0;
+        mainstruct.turbine.turbine_speed += 1.0 * mainstruct.turbine.steam_flow_rate * dt;
+    } else if mainstruct.turbine.turbine_speed > (mainstruct.turbine.setpoint_speed + dt) {
+        mainstruct.turbine.turbine_speed -= dt;
     } else {
         mainstruct.turbine.turbine_speed = mainstruct.turbine.setpoint_speed;
     }
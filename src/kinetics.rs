@@ -0,0 +1,251 @@
+use rayon::prelude::*;
+
+use crate::layout::fuel_channels;
+use crate::structs::{FuelRod, MainStruct, AXIAL_NODES, DELAYED_GROUPS};
+
+// thermal power of the core at 100%, in MW
+pub const NOMINAL_THERMAL_POWER: f32 = 3200.0;
+// the largest step the implicit solver takes, in s
+const MAX_SUBSTEP: f64 = 0.01;
+
+pub fn point_kinetics(mainstruct: &mut MainStruct, dt: f32) {
+    let kinetics = &mut mainstruct.core.kinetics;
+    let rho = kinetics.reactivity.total();
+    let beta: f64 = DELAYED_GROUPS.iter().map(|group| group.0).sum();
+    let generation_time = kinetics.generation_time;
+    let source = kinetics.source;
+
+    // keep every substep well inside the prompt time constant
+    let prompt_rate = ((rho - beta) / generation_time).abs();
+    let substeps = ((dt as f64 / MAX_SUBSTEP).ceil())
+        .max((dt as f64 * prompt_rate * 4.0).ceil())
+        .max(1.0) as usize;
+    let h = dt as f64 / substeps as f64;
+
+    let previous_population = kinetics.neutron_population;
+    for _ in 0..substeps {
+        let (population, precursors) = implicit_step(
+            kinetics.neutron_population,
+            &kinetics.precursors,
+            rho,
+            generation_time,
+            source,
+            h,
+        );
+        kinetics.neutron_population = population;
+        kinetics.precursors = precursors;
+    }
+
+    // e-folding time of the population over the step, infinite when it holds steady
+    let growth = (kinetics.neutron_population / previous_population.max(f64::MIN_POSITIVE)).ln();
+    let period = if growth.abs() > 1.0e-9 {
+        dt as f64 / growth
+    } else {
+        f64::INFINITY
+    };
+    mainstruct.data.period = period as f32;
+    mainstruct.data.startup_rate = startup_rate(period) as f32;
+
+    let population = kinetics.neutron_population as f32;
+    mainstruct.data.reactivity = rho as f32;
+    mainstruct.data.neutron_flux = population * 100.0;
+    mainstruct.core.neutron_flux = population * 100.0;
+    mainstruct.core.fission_power = population * 100.0;
+
+    channel_flux(mainstruct);
+}
+
+/// Startup rate in decades per minute from the reactor period in s.
+pub fn startup_rate(period: f64) -> f64 {
+    60.0 / (period * std::f64::consts::LN_10)
+}
+
+/// One backward Euler step of the point kinetics equations.
+/// Solving for the new precursors first leaves a single linear equation for the population,
+/// which keeps the step stable however stiff the prompt term is.
+/// The external source keeps a subcritical core at population = source * generation_time / -rho.
+pub fn implicit_step(
+    population: f64,
+    precursors: &[f64; 6],
+    rho: f64,
+    generation_time: f64,
+    source: f64,
+    h: f64,
+) -> (f64, [f64; 6]) {
+    let beta: f64 = DELAYED_GROUPS.iter().map(|group| group.0).sum();
+    let mut denominator = 1.0 - h * (rho - beta) / generation_time;
+    let mut numerator = population + h * source;
+    for (i, (beta_i, lambda_i)) in DELAYED_GROUPS.iter().enumerate() {
+        let decay = 1.0 + lambda_i * h;
+        numerator += h * lambda_i * precursors[i] / decay;
+        denominator -= h * h * lambda_i * beta_i / (generation_time * decay);
+    }
+    let new_population = (numerator / denominator).max(0.0);
+
+    let mut new_precursors = [0.0; 6];
+    for (i, (beta_i, lambda_i)) in DELAYED_GROUPS.iter().enumerate() {
+        new_precursors[i] =
+            (precursors[i] + h * beta_i / generation_time * new_population) / (1.0 + lambda_i * h);
+    }
+    (new_population, new_precursors)
+}
+
+/// Average a per node quantity over the fuel channels, weighting each axial node by its flux.
+/// `value` gets the channel and the index of the node in it.
+pub fn flux_weighted<F>(mainstruct: &MainStruct, value: F) -> f64
+where
+    F: Fn(&FuelRod, usize) -> f64 + Sync,
+{
+    // the rows are added up in order, so the sum comes out the same on every run
+    let (weighted, total_flux) = mainstruct
+        .absorber_rods
+        .par_iter()
+        .map(|row| {
+            row.iter()
+                .filter(|rod| rod.cell_type.is_fuel())
+                .fold((0.0, 0.0), |acc, rod| {
+                    (0..AXIAL_NODES).fold(acc, |acc, k| {
+                        let flux = (rod.neutron_flux * rod.nodes[k].flux) as f64;
+                        (acc.0 + flux * value(rod, k), acc.1 + flux)
+                    })
+                })
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .fold((0.0, 0.0), |a, b| (a.0 + b.0, a.1 + b.1));
+    if total_flux > 0.0 {
+        weighted / total_flux
+    } else {
+        0.0
+    }
+}
+
+fn channel_flux(mainstruct: &mut MainStruct) {
+    // the amplitude comes from the kinetics, the shape from the last diffusion solve
+    let channel_power = NOMINAL_THERMAL_POWER / fuel_channels(mainstruct) as f32;
+    let population = mainstruct.core.kinetics.neutron_population as f32;
+    let diffusion = &mainstruct.core.diffusion;
+    mainstruct
+        .absorber_rods
+        .par_iter_mut()
+        .enumerate()
+        .for_each(|(i, row)| {
+            row.iter_mut().enumerate().for_each(|(j, rod)| {
+                rod.neutron_flux = population * diffusion.thermal_flux[i][j] as f32;
+                rod.fission_power = population * diffusion.power_shape[i][j] as f32 * channel_power;
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn equilibrium(population: f64, generation_time: f64) -> [f64; 6] {
+        let mut precursors = [0.0; 6];
+        for (i, (beta_i, lambda_i)) in DELAYED_GROUPS.iter().enumerate() {
+            precursors[i] = beta_i / (lambda_i * generation_time) * population;
+        }
+        precursors
+    }
+
+    #[test]
+    fn test_critical_holds_power() {
+        let generation_time = 1.0e-3;
+        let mut population = 1.0;
+        let mut precursors = equilibrium(population, generation_time);
+        for _ in 0..1000 {
+            (population, precursors) =
+                implicit_step(population, &precursors, 0.0, generation_time, 0.0, 0.01);
+        }
+        assert!((population - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_prompt_jump() {
+        // a step of rho jumps to about beta / (beta - rho) in well under a second,
+        // then only rises slowly on the delayed neutrons
+        let generation_time = 1.0e-3;
+        let beta: f64 = DELAYED_GROUPS.iter().map(|group| group.0).sum();
+        let rho = 0.2 * beta;
+        let mut population = 1.0;
+        let mut precursors = equilibrium(population, generation_time);
+        for _ in 0..1000 {
+            (population, precursors) =
+                implicit_step(population, &precursors, rho, generation_time, 0.0, 0.001);
+        }
+        let expected = beta / (beta - rho);
+        assert!(
+            population > expected && population < expected * 1.1,
+            "Expected: {}, got: {}",
+            expected,
+            population
+        );
+    }
+
+    #[test]
+    fn test_stable_period() {
+        // 0.1% dk/k is a little over 50 s of stable period for U-235 delayed data
+        let generation_time = 1.0e-3;
+        let rho = 0.001;
+        let mut population = 1.0;
+        let mut precursors = equilibrium(population, generation_time);
+        for _ in 0..30000 {
+            (population, precursors) =
+                implicit_step(population, &precursors, rho, generation_time, 0.0, 0.01);
+        }
+        let before = population;
+        for _ in 0..1000 {
+            (population, precursors) =
+                implicit_step(population, &precursors, rho, generation_time, 0.0, 0.01);
+        }
+        let period = 10.0 / (population / before).ln();
+        assert!(period > 50.0 && period < 65.0, "period: {}", period);
+    }
+
+    #[test]
+    fn test_subcritical_multiplication() {
+        // with a source the population settles at source * generation_time / -rho,
+        // so halving the shutdown margin doubles the count rate
+        let generation_time = 1.0e-3;
+        let source = 1.0e-7;
+        for rho in [-0.02, -0.01] {
+            let mut population = 0.0;
+            let mut precursors = [0.0; 6];
+            for _ in 0..5000 {
+                (population, precursors) =
+                    implicit_step(population, &precursors, rho, generation_time, source, 1.0);
+            }
+            let expected = source * generation_time / -rho;
+            assert!(
+                (population - expected).abs() < expected * 1.0e-3,
+                "Expected: {}, got: {}",
+                expected,
+                population
+            );
+        }
+    }
+
+    #[test]
+    fn test_period_and_startup_rate() {
+        // a 26 s period is one decade a minute
+        assert!((startup_rate(26.06) - 1.0).abs() < 1.0e-3);
+        assert_eq!(startup_rate(f64::INFINITY), 0.0);
+
+        let mut mainstruct = MainStruct::default();
+        mainstruct.core.kinetics.source = 0.0;
+        mainstruct.core.kinetics.reactivity.lattice = 0.001;
+        let (width, height) = (
+            mainstruct.core.width as usize,
+            mainstruct.core.height as usize,
+        );
+        mainstruct.core.diffusion.thermal_flux = vec![vec![1.0; width]; height];
+        mainstruct.core.diffusion.power_shape = vec![vec![1.0; width]; height];
+        for _ in 0..300 {
+            point_kinetics(&mut mainstruct, 1.0);
+        }
+        let period = mainstruct.data.period;
+        assert!(period > 50.0 && period < 65.0, "period: {}", period);
+        assert!(mainstruct.data.startup_rate > 0.3 && mainstruct.data.startup_rate < 0.6);
+    }
+}
@@ -0,0 +1,257 @@
+use crate::layout::{CellType, RodGroup};
+use crate::malfunction::failed_detectors;
+use crate::structs::{FuelRod, MainStruct, Zone};
+
+// LAR rod travel per s for each unit of relative power off the zone average, in %
+const GAIN: f32 = 10.0;
+// relative power difference the zones are left with
+const DEADBAND: f32 = 0.02;
+// fastest the demand may move, in % of the travel per s
+const MAX_RATE: f32 = 0.5;
+
+/// A zone around each LAR rod, read by the detectors on the sides of the rod:
+/// the detector channel beside it and those in the central tubes of the fuel assemblies around it.
+pub fn build_zones(absorber_rods: &[Vec<FuelRod>]) -> Vec<Zone> {
+    cells_of(absorber_rods, |cell_type| {
+        cell_type.rod_group() == Some(RodGroup::LocalAutomatic)
+    })
+    .into_iter()
+    .map(|rod| Zone {
+        rod,
+        detectors: detectors_around(absorber_rods, rod),
+        reading: 1.0,
+        demand: absorber_rods[rod.0][rod.1].absorber_rod_position,
+    })
+    .collect()
+}
+
+/// The zone a cell belongs to, None when the core has no LAR rods.
+pub fn zone_of(zones: &[Zone], (row, col): (usize, usize)) -> Option<usize> {
+    let distance = |zone: &Zone| {
+        let (di, dj) = (
+            zone.rod.0 as f32 - row as f32,
+            zone.rod.1 as f32 - col as f32,
+        );
+        di * di + dj * dj
+    };
+    (0..zones.len()).min_by(|a, b| distance(&zones[*a]).total_cmp(&distance(&zones[*b])))
+}
+
+/// Hand the LAR rods to their zones, starting from where they are.
+pub fn enable_local_regulation(mainstruct: &mut MainStruct) {
+    let regulation = &mut mainstruct.core.local_regulation;
+    regulation.enabled = true;
+    for zone in regulation.zones.iter_mut() {
+        zone.demand = mainstruct.absorber_rods[zone.rod.0][zone.rod.1].absorber_rod_position;
+    }
+}
+
+/// Take the LAR rods back, leaving them where they are.
+pub fn disable_local_regulation(mainstruct: &mut MainStruct) {
+    mainstruct.core.local_regulation.enabled = false;
+    for zone in mainstruct.core.local_regulation.zones.iter() {
+        let rod = &mut mainstruct.absorber_rods[zone.rod.0][zone.rod.1];
+        rod.set_point = rod.absorber_rod_position;
+    }
+}
+
+/// Read the detectors of every zone and drive the LAR rods of zones above the average in,
+/// and of those below it out, flattening the radial power.
+pub fn local_regulation(mainstruct: &mut MainStruct, dt: f32) {
+    let failed = failed_detectors(mainstruct);
+    let readings: Vec<Option<f32>> = mainstruct
+        .core
+        .local_regulation
+        .zones
+        .iter()
+        .enumerate()
+        .map(|(index, zone)| {
+            // the detectors of a failed zone are all frozen at what they read as they failed
+            match failed.iter().find(|(failed_zone, _)| *failed_zone == index) {
+                Some((_, held)) => Some(*held),
+                None => zone_power(&mainstruct.absorber_rods, &zone.detectors),
+            }
+        })
+        .collect();
+    let seen: Vec<f32> = readings.iter().flatten().copied().collect();
+    let average = seen.iter().sum::<f32>() / seen.len().max(1) as f32;
+    for (zone, reading) in mainstruct
+        .core
+        .local_regulation
+        .zones
+        .iter_mut()
+        .zip(&readings)
+    {
+        zone.reading = match reading {
+            Some(power) if average > 0.0 => power / average,
+            _ => 1.0,
+        };
+    }
+
+    if mainstruct.core.scram && mainstruct.core.local_regulation.enabled {
+        disable_local_regulation(mainstruct);
+        mainstruct
+            .data
+            .log
+            .insert(0, "Local regulation off: scram".to_string());
+    }
+    if !mainstruct.core.local_regulation.enabled {
+        return;
+    }
+
+    let step = MAX_RATE * dt;
+    for zone in mainstruct.core.local_regulation.zones.iter_mut() {
+        // zones without a detector reading hold their rod
+        let error = zone.reading - 1.0;
+        if error.abs() > DEADBAND {
+            let change = (GAIN * (error - DEADBAND * error.signum()) * dt).clamp(-step, step);
+            zone.demand = (zone.demand + change).clamp(0.0, 100.0);
+        }
+        let rod = &mut mainstruct.absorber_rods[zone.rod.0][zone.rod.1];
+        rod.insert_rod = true;
+        rod.set_point = zone.demand;
+    }
+}
+
+fn detectors_around(absorber_rods: &[Vec<FuelRod>], (i, j): (usize, usize)) -> Vec<(usize, usize)> {
+    let (cells, present) = absorber_rods[i][j].neighbors;
+    cells
+        .into_iter()
+        .zip(present)
+        .filter(|(_, present)| *present)
+        .map(|((ni, nj), _)| (ni as usize, nj as usize))
+        .filter(|(ni, nj)| {
+            let cell_type = absorber_rods[*ni][*nj].cell_type;
+            cell_type == CellType::Detector || cell_type.is_fuel()
+        })
+        .collect()
+}
+
+fn cells_of<F>(absorber_rods: &[Vec<FuelRod>], matches: F) -> Vec<(usize, usize)>
+where
+    F: Fn(CellType) -> bool,
+{
+    absorber_rods
+        .iter()
+        .enumerate()
+        .flat_map(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, rod)| matches(rod.cell_type))
+                .map(move |(j, _)| (i, j))
+        })
+        .collect()
+}
+
+/// Average power of the fuel channels beside a detector, None without any.
+pub fn detector_power(absorber_rods: &[Vec<FuelRod>], (i, j): (usize, usize)) -> Option<f32> {
+    let (cells, present) = absorber_rods[i][j].neighbors;
+    let powers: Vec<f32> = cells
+        .into_iter()
+        .zip(present)
+        .filter(|(_, present)| *present)
+        .map(|((ni, nj), _)| &absorber_rods[ni as usize][nj as usize])
+        .filter(|rod| rod.cell_type.is_fuel())
+        .map(|rod| rod.fission_power)
+        .collect();
+    if powers.is_empty() {
+        return None;
+    }
+    Some(powers.iter().sum::<f32>() / powers.len() as f32)
+}
+
+/// Median of the detector readings of a zone, so a single detector reading wrong is outvoted.
+pub fn zone_power(absorber_rods: &[Vec<FuelRod>], detectors: &[(usize, usize)]) -> Option<f32> {
+    let mut powers: Vec<f32> = detectors
+        .iter()
+        .filter_map(|detector| detector_power(absorber_rods, *detector))
+        .collect();
+    if powers.is_empty() {
+        return None;
+    }
+    powers.sort_by(f32::total_cmp);
+    // the middle reading, or the mean of the middle two
+    let (low, high) = ((powers.len() - 1) / 2, powers.len() / 2);
+    Some((powers[low] + powers[high]) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{send_command, HELP_PAGE_LINES};
+
+    #[test]
+    fn test_every_zone_has_detectors() {
+        let mainstruct = MainStruct::default();
+        let zones = &mainstruct.core.local_regulation.zones;
+        assert_eq!(zones.len(), 12);
+        assert!(zones.iter().all(|zone| zone.detectors.len() > 2));
+        assert_eq!(zone_of(zones, zones[3].rod), Some(3));
+    }
+
+    #[test]
+    fn test_tilt_is_flattened() {
+        let mut mainstruct = MainStruct::default();
+        for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+            rod.absorber_rod_position = 50.0;
+            rod.fission_power = 1.0;
+        }
+        // the first zone runs hot
+        for (i, j) in mainstruct.core.local_regulation.zones[0].detectors.clone() {
+            for (ni, nj) in mainstruct.absorber_rods[i][j].neighbors.0 {
+                mainstruct.absorber_rods[ni as usize][nj as usize].fission_power = 1.5;
+            }
+        }
+        enable_local_regulation(&mut mainstruct);
+        local_regulation(&mut mainstruct, 1.0);
+        let zones = &mainstruct.core.local_regulation.zones;
+        assert!(zones[0].reading > 1.0 && zones[1].reading < 1.0);
+        assert_eq!(zones[0].demand, 50.0 + MAX_RATE);
+        assert!(zones[1].demand < 50.0);
+        let (ri, rj) = zones[0].rod;
+        assert_eq!(mainstruct.absorber_rods[ri][rj].set_point, 50.0 + MAX_RATE);
+    }
+
+    #[test]
+    fn test_one_detector_is_outvoted() {
+        let mut mainstruct = MainStruct::default();
+        for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+            rod.fission_power = 1.0;
+        }
+        // a hot channel on the far side of one detector, seen by none of the others
+        let zone = &mainstruct.core.local_regulation.zones[0];
+        let ((ri, rj), (di, dj)) = (zone.rod, zone.detectors[0]);
+        mainstruct.absorber_rods[2 * di - ri][2 * dj - rj].fission_power = 4.0;
+        let detectors = &mainstruct.core.local_regulation.zones[0].detectors;
+        assert_eq!(
+            detector_power(&mainstruct.absorber_rods, detectors[0]),
+            Some(2.0)
+        );
+        assert_eq!(zone_power(&mainstruct.absorber_rods, detectors), Some(1.0));
+    }
+
+    #[test]
+    fn test_frozen_detectors_drive_the_rod() {
+        let mut mainstruct = MainStruct::default();
+        for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+            rod.absorber_rod_position = 50.0;
+            rod.fission_power = 1.0;
+        }
+        enable_local_regulation(&mut mainstruct);
+        send_command(
+            "malfunction sensor failure detector 1",
+            &mut mainstruct,
+            HELP_PAGE_LINES,
+        );
+        // the power rises everywhere, the first zone still reads what it did as it failed
+        for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+            rod.fission_power = 2.0;
+        }
+        local_regulation(&mut mainstruct, 1.0);
+        let zones = &mainstruct.core.local_regulation.zones;
+        assert!(zones[0].reading < 1.0 && zones[1].reading > 1.0);
+        assert_eq!(zones[0].demand, 50.0 - MAX_RATE);
+        let (ri, rj) = zones[0].rod;
+        assert_eq!(mainstruct.absorber_rods[ri][rj].set_point, 50.0 - MAX_RATE);
+    }
+}
@@ -0,0 +1,287 @@
+use std::fs;
+
+use crate::lar::build_zones;
+use crate::orm::operational_margin;
+use crate::structs::{FuelRod, MainStruct};
+use serde::{Deserialize, Serialize};
+
+// the layout the simulation starts with, one character per cell
+pub const DEFAULT_LAYOUT: &str = include_str!("../resources/core_layout.txt");
+
+/// What occupies a cell of the core lattice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CellType {
+    Fuel,
+    ManualRod,
+    AutomaticRegulator,
+    LocalRegulator,
+    ShortenedAbsorber,
+    EmergencyRod,
+    Detector,
+    Empty,
+}
+
+impl CellType {
+    fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            'F' => Some(CellType::Fuel),
+            'R' => Some(CellType::ManualRod),
+            'A' => Some(CellType::AutomaticRegulator),
+            'L' => Some(CellType::LocalRegulator),
+            'U' => Some(CellType::ShortenedAbsorber),
+            'E' => Some(CellType::EmergencyRod),
+            'D' => Some(CellType::Detector),
+            '.' => Some(CellType::Empty),
+            _ => None,
+        }
+    }
+
+    fn symbol(self) -> char {
+        match self {
+            CellType::Fuel => 'F',
+            CellType::ManualRod => 'R',
+            CellType::AutomaticRegulator => 'A',
+            CellType::LocalRegulator => 'L',
+            CellType::ShortenedAbsorber => 'U',
+            CellType::EmergencyRod => 'E',
+            CellType::Detector => 'D',
+            CellType::Empty => '.',
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CellType::Fuel => "Fuel channel",
+            CellType::ManualRod => "Manual rod (RR)",
+            CellType::AutomaticRegulator => "Automatic regulator (AR)",
+            CellType::LocalRegulator => "Local automatic regulator (LAR)",
+            CellType::ShortenedAbsorber => "Shortened absorber (USP)",
+            CellType::EmergencyRod => "Emergency rod (AZ)",
+            CellType::Detector => "Detector",
+            CellType::Empty => "Graphite",
+        }
+    }
+
+    pub fn is_fuel(self) -> bool {
+        self == CellType::Fuel
+    }
+
+    /// Any of the control and protection rods.
+    pub fn is_rod(self) -> bool {
+        self.rod_group().is_some()
+    }
+
+    pub fn rod_group(self) -> Option<RodGroup> {
+        match self {
+            CellType::ManualRod => Some(RodGroup::Manual),
+            CellType::AutomaticRegulator => Some(RodGroup::Automatic),
+            CellType::LocalRegulator => Some(RodGroup::LocalAutomatic),
+            CellType::ShortenedAbsorber => Some(RodGroup::Shortened),
+            CellType::EmergencyRod => Some(RodGroup::Emergency),
+            _ => None,
+        }
+    }
+}
+
+/// The groups of the control and protection system, each on drives of its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RodGroup {
+    Manual,
+    Automatic,
+    LocalAutomatic,
+    Shortened,
+    Emergency,
+}
+
+pub const ROD_GROUPS: [RodGroup; 5] = [
+    RodGroup::Manual,
+    RodGroup::Automatic,
+    RodGroup::LocalAutomatic,
+    RodGroup::Shortened,
+    RodGroup::Emergency,
+];
+
+impl RodGroup {
+    pub fn name(self) -> &'static str {
+        match self {
+            RodGroup::Manual => "RR",
+            RodGroup::Automatic => "AR",
+            RodGroup::LocalAutomatic => "LAR",
+            RodGroup::Shortened => "USP",
+            RodGroup::Emergency => "AZ",
+        }
+    }
+
+    /// The group from its short name in any case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        ROD_GROUPS
+            .into_iter()
+            .find(|group| group.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Whether the rods of the group are driven to a set point,
+    /// the emergency rods only ever go all the way in.
+    pub fn takes_set_point(self) -> bool {
+        self != RodGroup::Emergency
+    }
+}
+
+/// Parse a layout, one row of cells per line, lines starting with # are comments.
+pub fn parse_layout(text: &str) -> Result<Vec<Vec<CellType>>, String> {
+    let mut layout: Vec<Vec<CellType>> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let row = line
+            .chars()
+            .enumerate()
+            .map(|(column, symbol)| {
+                CellType::from_symbol(symbol).ok_or(format!(
+                    "line {}, column {}: unknown cell '{}'",
+                    number + 1,
+                    column + 1,
+                    symbol
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(first) = layout.first() {
+            if row.len() != first.len() {
+                return Err(format!(
+                    "line {}: {} cells where the first row has {}",
+                    number + 1,
+                    row.len(),
+                    first.len()
+                ));
+            }
+        }
+        layout.push(row);
+    }
+    if layout.is_empty() {
+        return Err("the layout has no cells".to_string());
+    }
+    if !layout.iter().flatten().any(|cell| cell.is_fuel()) {
+        return Err("the layout has no fuel channels".to_string());
+    }
+    Ok(layout)
+}
+
+pub fn load_layout(path: &str) -> Result<Vec<Vec<CellType>>, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    parse_layout(&text)
+}
+
+/// Replace the lattice with fresh cells laid out as given.
+pub fn apply_layout(mainstruct: &mut MainStruct, layout: &[Vec<CellType>]) {
+    let height = layout.len();
+    let width = layout[0].len();
+    mainstruct.absorber_rods = layout
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(j, cell_type)| {
+                    let mut rod = FuelRod {
+                        cell_type: *cell_type,
+                        ..Default::default()
+                    };
+                    // the rods hold where they start until given a set point
+                    if cell_type.is_rod() {
+                        rod.set_point = rod.absorber_rod_position;
+                    }
+                    // up, down, left, right
+                    for (k, (di, dj)) in [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().enumerate() {
+                        let (ni, nj) = (i as i32 + di, j as i32 + dj);
+                        if ni >= 0 && nj >= 0 && ni < height as i32 && nj < width as i32 {
+                            rod.neighbors.0[k] = (ni as u16, nj as u16);
+                            rod.neighbors.1[k] = true;
+                        }
+                    }
+                    rod
+                })
+                .collect()
+        })
+        .collect();
+    mainstruct.core.width = width as u16;
+    mainstruct.core.height = height as u16;
+    mainstruct.core.selected_rod = 0;
+    mainstruct.core.diffusion = Default::default();
+    mainstruct.core.local_regulation.zones = build_zones(&mainstruct.absorber_rods);
+    // the margin is read before the first step, by the console and by scenario objectives
+    operational_margin(mainstruct);
+}
+
+/// A fingerprint of the lattice the plant runs on, the same for the same cells on every build.
+pub fn layout_checksum(mainstruct: &MainStruct) -> u64 {
+    // 64 bit FNV-1a over the cell symbols, row by row
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    mainstruct
+        .absorber_rods
+        .iter()
+        .flat_map(|row| row.iter().map(|rod| rod.cell_type.symbol()).chain(['\n']))
+        .fold(OFFSET_BASIS, |hash, symbol| {
+            (hash ^ symbol as u64).wrapping_mul(PRIME)
+        })
+}
+
+/// Fuel channels in the core.
+pub fn fuel_channels(mainstruct: &MainStruct) -> usize {
+    mainstruct
+        .absorber_rods
+        .iter()
+        .flatten()
+        .filter(|rod| rod.cell_type.is_fuel())
+        .count()
+}
+
+/// Row and column of a cell from the number shown on the map, counted from 1.
+pub fn cell_position(mainstruct: &MainStruct, number: usize) -> Option<(usize, usize)> {
+    let width = mainstruct.core.width as usize;
+    let cells = width * mainstruct.core.height as usize;
+    if number == 0 || number > cells {
+        return None;
+    }
+    Some(((number - 1) / width, (number - 1) % width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_layout() {
+        let layout = parse_layout(DEFAULT_LAYOUT).unwrap();
+        assert_eq!((layout.len(), layout[0].len()), (48, 48));
+        let count = |cell_type: CellType| {
+            layout
+                .iter()
+                .flatten()
+                .filter(|cell| **cell == cell_type)
+                .count()
+        };
+        let channels = layout
+            .iter()
+            .flatten()
+            .filter(|cell| **cell != CellType::Empty)
+            .count();
+        assert_eq!(channels, 1884);
+        assert_eq!(count(CellType::Fuel), 1664);
+        assert_eq!(
+            layout.iter().flatten().filter(|cell| cell.is_rod()).count(),
+            208
+        );
+        assert_eq!(count(CellType::LocalRegulator), 12);
+        assert_eq!(count(CellType::Detector), 12);
+    }
+
+    #[test]
+    fn test_bad_layouts() {
+        assert!(parse_layout("FF\nF").is_err());
+        assert!(parse_layout("FX").is_err());
+        assert!(parse_layout("# nothing\n").is_err());
+        assert!(parse_layout("RR\n..").is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use crate::structs::MainStruct;
+
+// the full plant, which a fresh plant runs at
+pub const MAX_LEVEL: u8 = 3;
+
+/// A part of the plant that is only simulated from some level up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Subsystem {
+    /// heat from the decay of the fission products
+    DecayHeat,
+    /// the iodine and xenon chain
+    Xenon,
+    /// water amounts in the deaerator and condenser
+    WaterInventory,
+    /// depletion of the fuel, and the flux following its composition
+    Burnup,
+    Malfunctions,
+}
+
+impl Subsystem {
+    /// The lowest level the subsystem runs at.
+    pub fn level(self) -> u8 {
+        match self {
+            Subsystem::DecayHeat | Subsystem::Xenon | Subsystem::WaterInventory => 1,
+            Subsystem::Burnup => 2,
+            Subsystem::Malfunctions => 3,
+        }
+    }
+}
+
+pub fn enabled(mainstruct: &MainStruct, subsystem: Subsystem) -> bool {
+    mainstruct.level >= subsystem.level()
+}
+
+/// Run the plant at another level, repairing the malfunctions when they are switched off.
+pub fn set_level(mainstruct: &mut MainStruct, level: u8) -> Result<(), String> {
+    if level > MAX_LEVEL {
+        return Err(format!("there is no level {}", level));
+    }
+    mainstruct.level = level;
+    if !enabled(mainstruct, Subsystem::Malfunctions) && !mainstruct.malfunctions.is_empty() {
+        mainstruct.malfunctions.clear();
+        mainstruct
+            .data
+            .log
+            .insert(0, "Malfunctions repaired".to_string());
+    }
+    mainstruct
+        .data
+        .log
+        .insert(0, format!("Simulation level {}", level));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::tick;
+    use crate::commands::{send_command, HELP_PAGE_LINES};
+
+    #[test]
+    fn test_level_zero() {
+        let mut mainstruct = MainStruct::default();
+        send_command("malfunction turbine trip", &mut mainstruct, HELP_PAGE_LINES);
+        assert_eq!(mainstruct.malfunctions.len(), 1);
+        send_command("level 0", &mut mainstruct, HELP_PAGE_LINES);
+        assert!(mainstruct.malfunctions.is_empty());
+        send_command("malfunction turbine trip", &mut mainstruct, HELP_PAGE_LINES);
+        assert!(mainstruct.malfunctions.is_empty());
+
+        send_command("pull rods", &mut mainstruct, HELP_PAGE_LINES);
+        for _ in 0..60 {
+            tick(&mut mainstruct, 1.0);
+        }
+        let reactivity = &mainstruct.core.kinetics.reactivity;
+        assert_eq!((reactivity.xenon, reactivity.burnup), (0.0, 0.0));
+        assert_eq!(mainstruct.core.decay_heat, 0.0);
+        assert!(mainstruct.core.thermal_power > 0.0);
+        // nothing past the turbine is run
+        assert_eq!(mainstruct.deaerator.water_level, 0.0);
+        assert_eq!(mainstruct.condenser.pressure, 0.0);
+    }
+}
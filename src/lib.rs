@@ -0,0 +1,141 @@
+//! The RBMK-1000 plant model without a front end.
+//! A [`Simulation`] is advanced in steps of simulated time and driven by the same commands
+//! the operator types at the console, with the whole plant state open to read.
+
+pub mod alarms;
+pub mod axial;
+pub mod burnup;
+pub mod clock;
+pub mod commands;
+pub mod conduction;
+pub mod decay;
+pub mod diffusion;
+pub mod geometry;
+pub mod interpolate;
+pub mod kinetics;
+pub mod lar;
+pub mod layout;
+pub mod level;
+pub mod malfunction;
+pub mod moderator;
+pub mod orm;
+pub mod regulator;
+pub mod rods;
+pub mod scenario;
+pub mod session;
+pub mod snapshot;
+pub mod startup;
+pub mod steam;
+pub mod structs;
+pub mod xenon;
+
+use std::path::Path;
+
+use crate::clock::{advance, tick};
+use crate::commands::{send_command, HELP_PAGE_LINES};
+use crate::layout::{apply_layout, CellType};
+use crate::level::set_level;
+use crate::scenario::{start_scenario, Scenario};
+use crate::session::{start_recording, start_replay};
+use crate::structs::MainStruct;
+
+/// A running plant.
+#[derive(Clone, Debug, Default)]
+pub struct Simulation {
+    state: MainStruct,
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A plant on another core lattice, see [`layout::parse_layout`].
+    pub fn with_layout(layout: &[Vec<CellType>]) -> Self {
+        let mut simulation = Self::default();
+        apply_layout(&mut simulation.state, layout);
+        simulation
+    }
+
+    /// Advance every model by dt seconds of simulated time, whatever the clock is set to.
+    pub fn step(&mut self, dt: f32) {
+        tick(&mut self.state, dt);
+    }
+
+    /// Run the clock for `elapsed` seconds of wall time, at its speed and in its fixed steps,
+    /// as the console does between frames.
+    pub fn advance(&mut self, elapsed: f32) {
+        advance(&mut self.state, elapsed);
+    }
+
+    /// Apply an operator command, as typed at the console. Anything it reports goes to the log.
+    pub fn apply_command(&mut self, command: &str) {
+        send_command(command, &mut self.state, HELP_PAGE_LINES);
+    }
+
+    /// Run the plant at another simulation level, see [`level`].
+    pub fn set_level(&mut self, level: u8) -> Result<(), String> {
+        set_level(&mut self.state, level)
+    }
+
+    /// Set the plant up as a scenario starts it, its events and objectives then run with the clock.
+    pub fn start_scenario(&mut self, scenario: Scenario) -> Result<(), String> {
+        start_scenario(&mut self.state, scenario)
+    }
+
+    /// Record the commands given from here on to a session file, see [`session`].
+    pub fn start_recording(&mut self, path: &Path) -> Result<(), String> {
+        start_recording(&mut self.state, path)
+    }
+
+    /// Run a session file back on this plant, which should be set up as the recorded one was.
+    pub fn start_replay(&mut self, path: &Path) -> Result<(), String> {
+        start_replay(&mut self.state, path)
+    }
+
+    pub fn state(&self) -> &MainStruct {
+        &self.state
+    }
+
+    /// The state for front ends that keep what they show in it, like the console's log and
+    /// checklist. Changing the plant through it skips the checks the commands make.
+    pub fn state_mut(&mut self) -> &mut MainStruct {
+        &mut self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headless_run() {
+        let mut simulation = Simulation::new();
+        simulation.apply_command("setpoint 50");
+        for _ in 0..5 {
+            simulation.step(1.0);
+        }
+        let state = simulation.state();
+        assert_eq!(state.clock.time, 5.0);
+        let rod = &state.absorber_rods[22][1];
+        assert!(rod.absorber_rod_position < 100.0 && rod.set_point == 50.0);
+    }
+
+    #[test]
+    fn test_idle_plant_stays_shut_down() {
+        // with no commands given the rods hold where they start, fully in
+        let mut simulation = Simulation::new();
+        for _ in 0..60 {
+            simulation.step(1.0);
+        }
+        let state = simulation.state();
+        assert!(state
+            .absorber_rods
+            .iter()
+            .flatten()
+            .filter(|rod| rod.cell_type.is_rod())
+            .all(|rod| rod.absorber_rod_position == 100.0));
+        assert!(state.core.kinetics.reactivity.total() < 0.0);
+        assert!(state.core.thermal_power < 1.0, "{}", state.core.thermal_power);
+    }
+}
@@ -1,11 +1,20 @@
-use crate::steam::steam;
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use interpolate::{fuel_temperature, graphline, neutron_flux, neutron_rate, turbine};
-use std::{io, sync::mpsc::channel, thread, time::Duration, vec};
+use rbmk::clock::clock_text;
+use rbmk::commands::send_command;
+use rbmk::layout::load_layout;
+use rbmk::scenario::load_scenario;
+use rbmk::structs::MainStruct;
+use rbmk::Simulation;
+use std::{
+    io,
+    path::Path,
+    time::{Duration, Instant},
+    vec,
+};
 use tui::{
     backend::CrosstermBackend,
     style::{Color, Style},
@@ -16,24 +25,69 @@ use tui::{
 };
 
 // import local modules
-use crate::commands::send_command;
 use crate::draw::draw;
-use crate::interpolate::interpolate_position;
-use crate::structs::MainStruct;
 
+// the terminal front end, the plant model is the rbmk library
 mod arcfm;
-mod commands;
 mod draw;
-mod interpolate;
-mod steam;
-mod structs;
 mod svg;
 
 fn main() -> Result<(), io::Error> {
+    let mut simulation = Simulation::new();
+    // another core lattice can be given with --layout <file>
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--layout") {
+        let path = args
+            .get(index + 1)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "--layout needs a file"))?;
+        let layout =
+            load_layout(path).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        simulation = Simulation::with_layout(&layout);
+    }
+    // a training scenario with --scenario <file>
+    if let Some(index) = args.iter().position(|arg| arg == "--scenario") {
+        let path = args.get(index + 1).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "--scenario needs a file")
+        })?;
+        let scenario =
+            load_scenario(path).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        simulation
+            .start_scenario(scenario)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    }
+    // the simulation level with --level <0-3>, the full plant without it,
+    // set after the scenario so the snapshot it may start from does not undo it
+    if let Some(index) = args.iter().position(|arg| arg == "--level") {
+        let level = args
+            .get(index + 1)
+            .and_then(|level| level.parse::<u8>().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "--level needs 0 to 3"))?;
+        simulation
+            .set_level(level)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidInput, error))?;
+    }
+    // a session is recorded with --record <file> and run back with --replay <file>,
+    // on a plant started with the same --layout and --scenario
+    if let Some(index) = args.iter().position(|arg| arg == "--record") {
+        let path = args
+            .get(index + 1)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "--record needs a file"))?;
+        simulation
+            .start_recording(Path::new(path))
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--replay") {
+        let path = args
+            .get(index + 1)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "--replay needs a file"))?;
+        simulation
+            .start_replay(Path::new(path))
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    }
+
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    let mut mainstruct = MainStruct::default();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
@@ -43,11 +97,7 @@ fn main() -> Result<(), io::Error> {
     // Create a new menu
 
     //let mut fuel_rods = vec![vec![FuelRod::default(); 5]; 5];
-    let (tx, rx) = channel();
-    thread::spawn(move || loop {
-        tx.send(()).unwrap();
-        thread::sleep(Duration::from_secs(1));
-    });
+    let mut last_frame = Instant::now();
 
     let mut previous_commands: (Vec<Vec<String>>, i32) = (Vec::new(), 0);
     let mut labels = Vec::new();
@@ -67,15 +117,12 @@ fn main() -> Result<(), io::Error> {
         }
     }
     loop {
-        while rx.try_recv().is_ok() {
-            interpolate_position(&mut mainstruct);
-            graphline(&mut mainstruct);
-            neutron_rate(&mut mainstruct);
-            neutron_flux(&mut mainstruct);
-            fuel_temperature(&mut mainstruct);
-            steam(&mut mainstruct);
-            turbine(&mut mainstruct);
-        }
+        // the physics runs in fixed steps of simulated time, however long a frame takes
+        let now = Instant::now();
+        let elapsed = now.duration_since(last_frame).as_secs_f32();
+        simulation.advance(elapsed);
+        last_frame = now;
+        let mainstruct = simulation.state_mut();
         let graphs = mainstruct.data.graphs.clone();
         let datasets = vec![
             Dataset::default()
@@ -107,11 +154,35 @@ fn main() -> Result<(), io::Error> {
                 .marker(symbols::Marker::Braille)
                 .graph_type(GraphType::Line)
                 .style(Style::default().fg(Color::Green))
-                .data(&[]), 
+                .data(&graphs[4]),
         ];
 
+        let period = if mainstruct.data.period.is_finite() {
+            format!("{:.1}s", mainstruct.data.period)
+        } else {
+            "inf".to_string()
+        };
+        let paused = if mainstruct.clock.paused {
+            " paused"
+        } else {
+            ""
+        };
+        let title = format!(
+            "Trend Chart - T+{} {}x{} - Period: {} SUR: {:.2}dpm ORM: {:.1}{}",
+            clock_text(mainstruct),
+            mainstruct.clock.speed,
+            paused,
+            period,
+            mainstruct.data.startup_rate,
+            mainstruct.core.orm.margin,
+            if mainstruct.data.alarms.is_empty() {
+                String::new()
+            } else {
+                format!(" - {}", mainstruct.data.alarms.join(", "))
+            }
+        );
         let graph = Chart::new(datasets)
-            .block(Block::default().title("Trend Chart").borders(Borders::ALL))
+            .block(Block::default().title(title).borders(Borders::ALL))
             .x_axis(
                 Axis::default()
                     .title("X Axis")
@@ -149,7 +220,7 @@ fn main() -> Result<(), io::Error> {
             &mut terminal,
             tui_command_text,
             block_2,
-            &mut mainstruct,
+            mainstruct,
             log_paragraph.clone(),
             graph.clone(),
         )
@@ -160,7 +231,7 @@ fn main() -> Result<(), io::Error> {
         read_input(
             &mut terminal,
             &mut command_text,
-            &mut mainstruct,
+            mainstruct,
             &mut previous_commands,
             height,
         )?;
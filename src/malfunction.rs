@@ -0,0 +1,391 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::kinetics::startup_rate;
+use crate::lar::zone_power;
+use crate::layout::{cell_position, RodGroup, ROD_GROUPS};
+use crate::level::{enabled, Subsystem};
+use crate::scenario::{holds, Condition, Quantity, Trigger};
+use crate::structs::MainStruct;
+use serde::{Deserialize, Serialize};
+
+// a slow rod moves at this fraction of the speed of its drive, also on a scram
+const SLOW_ROD_FACTOR: f32 = 0.25;
+// coolant flow the core keeps by natural circulation once the pumps have run down, in %
+const NATURAL_CIRCULATION: f32 = 10.0;
+// time constant of the pump run down on the flywheels, in s
+const COASTDOWN_TIME: f32 = 30.0;
+
+lazy_static! {
+    static ref MALFUNCTION: Regex = Regex::new(
+        r"^(.+?)(?: in (\d+(?:\.\d+)?)| when (\w+) (above|below) (-?\d+(?:\.\d+)?))?$"
+    )
+    .unwrap();
+    static ref FAILURE: Regex = Regex::new(
+        r"^(?:(stuck|slow) rod (\d+)|drive failure (rr|ar|lar|usp|az)|sensor failure (flux|period|source range|detector (\d+))|drain valve stuck|turbine trip|pump trip)$"
+    )
+    .unwrap();
+}
+
+/// What can fail, by rod number, group or detector zone as the console counts them.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Failure {
+    /// the rod does not move at all, not even on a scram
+    StuckRod(usize),
+    SlowRod(usize),
+    /// the group cannot be driven, its rods still drop on a scram
+    DriveFailure(RodGroup),
+    DrainValveStuck,
+    /// the stop valves close and the turbine runs down
+    TurbineTrip,
+    /// the main circulation pumps run down to natural circulation
+    PumpTrip,
+    /// the reading freezes where it was
+    SensorFailure(Sensor),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Sensor {
+    Flux,
+    Period,
+    SourceRange,
+    Detector(usize),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Malfunction {
+    /// the part that fails and how
+    pub failure: Failure,
+    /// holds the failure back until it is met, None once it has failed
+    pub trigger: Option<Trigger>,
+    /// the valve position, flow or reading the failed part is stuck at
+    pub held: f32,
+}
+
+impl Failure {
+    /// The failure as it is given to the malfunction command.
+    pub fn name(self) -> String {
+        match self {
+            Failure::StuckRod(rod) => format!("stuck rod {}", rod),
+            Failure::SlowRod(rod) => format!("slow rod {}", rod),
+            Failure::DriveFailure(group) => {
+                format!("drive failure {}", group.name().to_lowercase())
+            }
+            Failure::DrainValveStuck => "drain valve stuck".to_string(),
+            Failure::TurbineTrip => "turbine trip".to_string(),
+            Failure::PumpTrip => "pump trip".to_string(),
+            Failure::SensorFailure(Sensor::Flux) => "sensor failure flux".to_string(),
+            Failure::SensorFailure(Sensor::Period) => "sensor failure period".to_string(),
+            Failure::SensorFailure(Sensor::SourceRange) => {
+                "sensor failure source range".to_string()
+            }
+            Failure::SensorFailure(Sensor::Detector(zone)) => {
+                format!("sensor failure detector {}", zone)
+            }
+        }
+    }
+}
+
+/// A failure and when it comes, from the text after `malfunction`, at the present time.
+pub fn parse_malfunction(text: &str, time: f64) -> Option<(Failure, Option<Trigger>)> {
+    let cap = MALFUNCTION.captures(text)?;
+    let failure = parse_failure(&cap[1])?;
+    let trigger = if let Some(delay) = cap.get(2) {
+        Some(Trigger::At(time + delay.as_str().parse::<f64>().ok()?))
+    } else if let Some(quantity) = cap.get(3) {
+        let quantity = Quantity::from_name(quantity.as_str())?;
+        let value = cap[5].parse::<f32>().ok()?;
+        Some(Trigger::When(match &cap[4] {
+            "above" => Condition::Above(quantity, value),
+            _ => Condition::Below(quantity, value),
+        }))
+    } else {
+        None
+    };
+    Some((failure, trigger))
+}
+
+fn parse_failure(text: &str) -> Option<Failure> {
+    let cap = FAILURE.captures(text)?;
+    let failure = if let Some(kind) = cap.get(1) {
+        let rod = cap[2].parse::<usize>().ok()?;
+        match kind.as_str() {
+            "stuck" => Failure::StuckRod(rod),
+            _ => Failure::SlowRod(rod),
+        }
+    } else if let Some(group) = cap.get(3) {
+        Failure::DriveFailure(RodGroup::from_name(group.as_str())?)
+    } else if let Some(sensor) = cap.get(4) {
+        Failure::SensorFailure(match sensor.as_str() {
+            "flux" => Sensor::Flux,
+            "period" => Sensor::Period,
+            "source range" => Sensor::SourceRange,
+            _ => Sensor::Detector(cap[5].parse::<usize>().ok()?),
+        })
+    } else {
+        match text {
+            "drain valve stuck" => Failure::DrainValveStuck,
+            "turbine trip" => Failure::TurbineTrip,
+            _ => Failure::PumpTrip,
+        }
+    };
+    Some(failure)
+}
+
+/// Arm a failure to come with its trigger, or fail it now without one.
+pub fn arm_malfunction(
+    mainstruct: &mut MainStruct,
+    failure: Failure,
+    trigger: Option<Trigger>,
+) -> Result<(), String> {
+    if !enabled(mainstruct, Subsystem::Malfunctions) {
+        return Err(format!(
+            "malfunctions need level {}",
+            Subsystem::Malfunctions.level()
+        ));
+    }
+    match failure {
+        Failure::StuckRod(rod) | Failure::SlowRod(rod) => {
+            let is_rod = cell_position(mainstruct, rod)
+                .is_some_and(|(i, j)| mainstruct.absorber_rods[i][j].cell_type.is_rod());
+            if !is_rod {
+                return Err(format!("cell {} is not a rod", rod));
+            }
+        }
+        Failure::SensorFailure(Sensor::Detector(zone))
+            if zone == 0 || zone > mainstruct.core.local_regulation.zones.len() =>
+        {
+            return Err(format!("there is no LAR zone {}", zone));
+        }
+        _ => {}
+    }
+    let mut malfunction = Malfunction {
+        failure,
+        trigger,
+        held: 0.0,
+    };
+    if malfunction.trigger.is_none() {
+        malfunction.held = held_value(mainstruct, failure);
+    }
+    mainstruct.malfunctions.push(malfunction);
+    Ok(())
+}
+
+/// Clear one malfunction by its number in the list, or all of them.
+/// What has failed stays where it was left, a tripped pump has to be started again.
+pub fn clear_malfunction(mainstruct: &mut MainStruct, number: Option<usize>) -> Result<(), String> {
+    match number {
+        Some(number) if number >= 1 && number <= mainstruct.malfunctions.len() => {
+            mainstruct.malfunctions.remove(number - 1);
+            Ok(())
+        }
+        Some(number) => Err(format!("there is no malfunction {}", number)),
+        None => {
+            mainstruct.malfunctions.clear();
+            Ok(())
+        }
+    }
+}
+
+/// Log the malfunctions, failed and armed, numbered for clearing.
+pub fn list_malfunctions(mainstruct: &mut MainStruct) {
+    let lines: Vec<String> = if mainstruct.malfunctions.is_empty() {
+        vec!["No malfunctions".to_string()]
+    } else {
+        mainstruct
+            .malfunctions
+            .iter()
+            .enumerate()
+            .map(|(i, malfunction)| {
+                let state = match &malfunction.trigger {
+                    None => "failed".to_string(),
+                    Some(Trigger::At(time)) => format!("armed for t = {:.0}s", time),
+                    Some(Trigger::When(condition)) => {
+                        format!("armed for when {}", condition_text(*condition))
+                    }
+                };
+                format!("{}: {} - {}", i + 1, malfunction.failure.name(), state)
+            })
+            .collect()
+    };
+    for line in lines {
+        mainstruct.data.log.insert(0, line);
+    }
+}
+
+/// Fail the armed malfunctions whose trigger is met and hold the failed plant where it failed,
+/// before the models run.
+pub fn malfunctions(mainstruct: &mut MainStruct, dt: f32) {
+    let time = mainstruct.clock.time;
+    for i in 0..mainstruct.malfunctions.len() {
+        let due = match &mainstruct.malfunctions[i].trigger {
+            None => false,
+            Some(Trigger::At(at)) => time >= *at,
+            Some(Trigger::When(condition)) => holds(mainstruct, *condition),
+        };
+        if due {
+            let failure = mainstruct.malfunctions[i].failure;
+            mainstruct.malfunctions[i].held = held_value(mainstruct, failure);
+            mainstruct.malfunctions[i].trigger = None;
+        }
+    }
+
+    let lag = (-dt / COASTDOWN_TIME).exp();
+    for malfunction in mainstruct
+        .malfunctions
+        .iter_mut()
+        .filter(|malfunction| malfunction.trigger.is_none())
+    {
+        match malfunction.failure {
+            Failure::DrainValveStuck => mainstruct.core.drain_valve = malfunction.held,
+            Failure::TurbineTrip => {
+                mainstruct.core.drain_valve = 0.0;
+                mainstruct.turbine.speed_setpoint_step = 0;
+            }
+            Failure::PumpTrip => {
+                malfunction.held =
+                    NATURAL_CIRCULATION + (malfunction.held - NATURAL_CIRCULATION) * lag;
+                mainstruct.core.coolant_flow = mainstruct.core.coolant_flow.min(malfunction.held);
+            }
+            _ => {}
+        }
+    }
+    // a trip closes the stop valves over a stuck drain valve
+    if mainstruct.malfunctions.iter().any(|malfunction| {
+        malfunction.trigger.is_none() && malfunction.failure == Failure::TurbineTrip
+    }) {
+        mainstruct.core.drain_valve = 0.0;
+    }
+}
+
+/// Put the frozen readings of failed sensors back over what the models have just worked out.
+pub fn failed_sensors(mainstruct: &mut MainStruct) {
+    for malfunction in active(&mainstruct.malfunctions) {
+        let held = malfunction.held;
+        match malfunction.failure {
+            Failure::SensorFailure(Sensor::Flux) => mainstruct.data.neutron_flux = held,
+            Failure::SensorFailure(Sensor::Period) => {
+                mainstruct.data.period = held;
+                mainstruct.data.startup_rate = startup_rate(held as f64) as f32;
+            }
+            Failure::SensorFailure(Sensor::SourceRange) => {
+                mainstruct.source_range.count_rate = held;
+                mainstruct.data.neutron_rate = held.max(1.0).log10();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The frozen power reading of each LAR zone with failed detectors, by zone index.
+pub fn failed_detectors(mainstruct: &MainStruct) -> Vec<(usize, f32)> {
+    active(&mainstruct.malfunctions)
+        .filter_map(|malfunction| match malfunction.failure {
+            Failure::SensorFailure(Sensor::Detector(zone)) => Some((zone - 1, malfunction.held)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The fraction of their drive speed stuck and slow rods move at, by cell.
+pub fn rod_mobility(mainstruct: &MainStruct) -> Vec<((usize, usize), f32)> {
+    active(&mainstruct.malfunctions)
+        .filter_map(|malfunction| match malfunction.failure {
+            Failure::StuckRod(rod) => Some((cell_position(mainstruct, rod)?, 0.0)),
+            Failure::SlowRod(rod) => Some((cell_position(mainstruct, rod)?, SLOW_ROD_FACTOR)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether the drives of each group have failed, in the order of ROD_GROUPS.
+pub fn failed_drives(mainstruct: &MainStruct) -> [bool; ROD_GROUPS.len()] {
+    ROD_GROUPS.map(|group| {
+        active(&mainstruct.malfunctions)
+            .any(|malfunction| malfunction.failure == Failure::DriveFailure(group))
+    })
+}
+
+fn active(malfunctions: &[Malfunction]) -> impl Iterator<Item = &Malfunction> {
+    malfunctions
+        .iter()
+        .filter(|malfunction| malfunction.trigger.is_none())
+}
+
+/// The value a failure holds on to, taken as it fails.
+fn held_value(mainstruct: &MainStruct, failure: Failure) -> f32 {
+    match failure {
+        Failure::DrainValveStuck => mainstruct.core.drain_valve,
+        Failure::PumpTrip => mainstruct.core.coolant_flow,
+        Failure::SensorFailure(Sensor::Flux) => mainstruct.data.neutron_flux,
+        Failure::SensorFailure(Sensor::Period) => mainstruct.data.period,
+        Failure::SensorFailure(Sensor::SourceRange) => mainstruct.source_range.count_rate,
+        Failure::SensorFailure(Sensor::Detector(zone)) => {
+            let detectors = &mainstruct.core.local_regulation.zones[zone - 1].detectors;
+            zone_power(&mainstruct.absorber_rods, detectors).unwrap_or(0.0)
+        }
+        _ => 0.0,
+    }
+}
+
+fn condition_text(condition: Condition) -> String {
+    match condition {
+        Condition::Above(quantity, value) => format!("{} above {}", quantity.name(), value),
+        Condition::Below(quantity, value) => format!("{} below {}", quantity.name(), value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpolate::interpolate_position;
+
+    #[test]
+    fn test_parse_malfunction() {
+        assert_eq!(
+            parse_malfunction("pump trip", 10.0).map(|(failure, _)| failure),
+            Some(Failure::PumpTrip)
+        );
+        assert!(matches!(
+            parse_malfunction("stuck rod 12 in 30", 10.0),
+            Some((Failure::StuckRod(12), Some(Trigger::At(at)))) if at == 40.0
+        ));
+        assert!(matches!(
+            parse_malfunction("drive failure ar when thermal_power above 50", 0.0),
+            Some((
+                Failure::DriveFailure(RodGroup::Automatic),
+                Some(Trigger::When(Condition::Above(Quantity::ThermalPower, _)))
+            ))
+        ));
+        assert!(parse_malfunction("sensor failure detector 3", 0.0).is_some());
+        assert!(parse_malfunction("broken kettle", 0.0).is_none());
+    }
+
+    #[test]
+    fn test_stuck_rod_and_pump_trip() {
+        let mut mainstruct = MainStruct::default();
+        let rod = 22 * mainstruct.core.width as usize + 2;
+        mainstruct.absorber_rods[22][1].absorber_rod_position = 50.0;
+        arm_malfunction(&mut mainstruct, Failure::StuckRod(rod), None).unwrap();
+        arm_malfunction(&mut mainstruct, Failure::PumpTrip, Some(Trigger::At(5.0))).unwrap();
+        mainstruct.core.scram = true;
+        malfunctions(&mut mainstruct, 1.0);
+        interpolate_position(&mut mainstruct, 1.0);
+        assert_eq!(mainstruct.absorber_rods[22][1].absorber_rod_position, 50.0);
+        assert_eq!(mainstruct.core.coolant_flow, 100.0);
+
+        mainstruct.clock.time = 5.0;
+        malfunctions(&mut mainstruct, 1.0);
+        malfunctions(&mut mainstruct, 1.0);
+        let flow = mainstruct.core.coolant_flow;
+        assert!(flow < 100.0 && flow > NATURAL_CIRCULATION);
+        // the pump cannot be run up again while it is tripped
+        mainstruct.core.coolant_flow = 100.0;
+        malfunctions(&mut mainstruct, 1.0);
+        assert!(mainstruct.core.coolant_flow < flow);
+
+        clear_malfunction(&mut mainstruct, Some(1)).unwrap();
+        interpolate_position(&mut mainstruct, 1.0);
+        assert!(mainstruct.absorber_rods[22][1].absorber_rod_position > 50.0);
+    }
+}
@@ -0,0 +1,84 @@
+use rayon::prelude::*;
+
+use crate::kinetics::flux_weighted;
+use crate::layout::CellType;
+use crate::steam::saturation_temperature;
+use crate::structs::{FuelRod, MainStruct};
+
+pub fn graphite_temperature(mainstruct: &mut MainStruct, dt: f32) {
+    // graphite above the coolant at 100% channel power in C, from the ~5.5% of the
+    // fission energy deposited in the moderator by neutron slowing down and gammas
+    const NOMINAL_RISE: f32 = 300.0;
+    // the stack is massive and only conducts through the channel gas gap, in s
+    const TIME_CONSTANT: f32 = 1200.0;
+
+    let coolant_temperature = saturation_temperature(mainstruct.core.steam.steam_pressure);
+    let lag = 1.0 - (-dt / TIME_CONSTANT).exp();
+
+    mainstruct.absorber_rods.par_iter_mut().for_each(|row| {
+        row.par_iter_mut().for_each(|rod| {
+            let equilibrium = coolant_temperature + rod.neutron_flux * NOMINAL_RISE;
+            rod.graphite_temperature += (equilibrium - rod.graphite_temperature) * lag;
+        });
+    });
+
+    // the graphite outside the channels is reflector, not part of the core average
+    let in_core = |rod: &&FuelRod| rod.cell_type != CellType::Empty;
+    let cells = mainstruct
+        .absorber_rods
+        .iter()
+        .flatten()
+        .filter(in_core)
+        .count() as f32;
+    // the rows are added up in order, so the sum comes out the same on every run
+    mainstruct.moderator.average_temperature = mainstruct
+        .absorber_rods
+        .par_iter()
+        .map(|row| {
+            row.iter()
+                .filter(in_core)
+                .map(|rod| rod.graphite_temperature)
+                .sum::<f32>()
+        })
+        .collect::<Vec<f32>>()
+        .iter()
+        .sum::<f32>()
+        / cells;
+
+    let coefficient = mainstruct.moderator.temperature_coefficient;
+    mainstruct.core.kinetics.reactivity.graphite = flux_weighted(mainstruct, |rod, _| {
+        graphite_worth(coefficient, rod.graphite_temperature)
+    });
+}
+
+/// Graphite temperature reactivity of a cell in dk/k, zero for a cold stack.
+pub fn graphite_worth(temperature_coefficient: f32, graphite_temperature: f32) -> f64 {
+    const REFERENCE_TEMPERATURE: f32 = 20.0;
+    (temperature_coefficient * (graphite_temperature - REFERENCE_TEMPERATURE)) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hot_stack_adds_reactivity() {
+        // at full flux the stack settles 300 C above the coolant, and the positive coefficient
+        // turns every degree above a cold stack into reactivity
+        let mut mainstruct = MainStruct::default();
+        for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+            rod.neutron_flux = 1.0;
+        }
+        for _ in 0..100 {
+            graphite_temperature(&mut mainstruct, 600.0);
+        }
+        let coolant = saturation_temperature(mainstruct.core.steam.steam_pressure);
+        let stack = mainstruct.absorber_rods[24][24].graphite_temperature;
+        assert!((stack - (coolant + 300.0)).abs() < 0.1, "stack: {}", stack);
+
+        let worth = mainstruct.core.kinetics.reactivity.graphite;
+        let expected = graphite_worth(mainstruct.moderator.temperature_coefficient, stack);
+        assert!((worth - expected).abs() < 1.0e-6, "worth: {}", worth);
+        assert!(worth > 0.003 && worth < 0.007, "worth: {}", worth);
+    }
+}
@@ -0,0 +1,84 @@
+use crate::rods::group_node_worth;
+use crate::structs::{FuelRod, MainStruct, AXIAL_NODES};
+
+/// Operational reactivity margin as the equivalent number of fully inserted rods.
+/// Each rod counts by how far it is in, weighted by the flux along it,
+/// and by the flux of its channel against the core average.
+pub fn operational_margin(mainstruct: &mut MainStruct) {
+    let rods = mainstruct
+        .absorber_rods
+        .iter()
+        .flatten()
+        .filter(|rod| rod.cell_type.is_rod());
+    let cells = rods.clone().count() as f32;
+    let mean_importance = rods
+        .clone()
+        .map(|rod| rod.neutron_flux * rod.neutron_flux)
+        .sum::<f32>()
+        / cells;
+
+    let equivalent: f32 = rods
+        .map(|rod| {
+            // a shut down core has no flux shape to speak of, so every rod counts the same
+            let radial = if mean_importance > 0.0 {
+                rod.neutron_flux * rod.neutron_flux / mean_importance
+            } else {
+                1.0
+            };
+            radial * insertion(rod)
+        })
+        .sum();
+    mainstruct.core.orm.margin = equivalent;
+}
+
+/// Worth of a rod at its position against the same rod fully inserted, along the axial shape.
+fn insertion(rod: &FuelRod) -> f32 {
+    let Some(group) = rod.cell_type.rod_group() else {
+        return 0.0;
+    };
+    let (worth, full) = (0..AXIAL_NODES).fold((0.0, 0.0), |acc, k| {
+        let importance = (rod.nodes[k].flux * rod.nodes[k].flux) as f64;
+        (
+            acc.0 + importance * group_node_worth(group, rod.absorber_rod_position, k),
+            acc.1 + importance * group_node_worth(group, 100.0, k),
+        )
+    });
+    if full > 0.0 {
+        (worth / full).max(0.0) as f32
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_rods(mainstruct: &mut MainStruct, position: f32) {
+        for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+            rod.absorber_rod_position = position;
+        }
+    }
+
+    #[test]
+    fn test_margin_follows_rods() {
+        let mut mainstruct = MainStruct::default();
+        let rods = mainstruct
+            .absorber_rods
+            .iter()
+            .flatten()
+            .filter(|rod| rod.cell_type.is_rod())
+            .count() as f32;
+        operational_margin(&mut mainstruct);
+        assert!((mainstruct.core.orm.margin - rods).abs() < 0.1);
+
+        set_rods(&mut mainstruct, 0.0);
+        operational_margin(&mut mainstruct);
+        assert!(mainstruct.core.orm.margin.abs() < 0.1);
+
+        set_rods(&mut mainstruct, 50.0);
+        operational_margin(&mut mainstruct);
+        let margin = mainstruct.core.orm.margin;
+        assert!(margin > 0.0 && margin < rods, "margin: {}", margin);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::layout::RodGroup;
+use crate::structs::MainStruct;
+
+// AR rod travel per % of power off the setpoint, in %
+const GAIN: f32 = 0.5;
+// time for the integral part to repeat the proportional part, in s
+const INTEGRAL_TIME: f32 = 30.0;
+// power error the loop leaves alone, in % of nominal
+const DEADBAND: f32 = 0.5;
+// fastest the demand may move, in % of the travel per s
+const MAX_RATE: f32 = 0.5;
+// highest thermal power the regulator may be set to hold, in % of nominal
+pub const MAX_SETPOINT: f32 = 100.0;
+
+/// Hand the AR rods to the regulator, holding the power as it is.
+/// The loop starts from where the rods are, so taking over does not move them.
+pub fn enable_regulator(mainstruct: &mut MainStruct) {
+    let position = group_position(mainstruct, RodGroup::Automatic);
+    let power = mainstruct.core.thermal_power;
+    let regulator = &mut mainstruct.core.regulator;
+    regulator.enabled = true;
+    regulator.setpoint = power;
+    regulator.demand = position;
+    regulator.integral = position;
+}
+
+/// Take the AR rods back, leaving them where the regulator left them.
+pub fn disable_regulator(mainstruct: &mut MainStruct) {
+    mainstruct.core.regulator.enabled = false;
+    for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+        if rod.cell_type.rod_group() == Some(RodGroup::Automatic) {
+            rod.set_point = rod.absorber_rod_position;
+        }
+    }
+}
+
+/// PI loop on the thermal power, driving the AR rods together towards the power setpoint.
+pub fn automatic_regulator(mainstruct: &mut MainStruct, dt: f32) {
+    if mainstruct.core.scram && mainstruct.core.regulator.enabled {
+        disable_regulator(mainstruct);
+        mainstruct
+            .data
+            .log
+            .insert(0, "Automatic regulator off: scram".to_string());
+    }
+    if !mainstruct.core.regulator.enabled {
+        return;
+    }
+
+    let power = mainstruct.core.thermal_power;
+    let regulator = &mut mainstruct.core.regulator;
+    // too much power drives the rods in
+    let error = power - regulator.setpoint;
+    let error = if error.abs() < DEADBAND {
+        0.0
+    } else {
+        error - DEADBAND * error.signum()
+    };
+    // the integral is held to the travel so it cannot wind up against the end stops
+    regulator.integral = (regulator.integral + GAIN * error * dt / INTEGRAL_TIME).clamp(0.0, 100.0);
+    let target = (regulator.integral + GAIN * error).clamp(0.0, 100.0);
+    let step = MAX_RATE * dt;
+    regulator.demand += (target - regulator.demand).clamp(-step, step);
+
+    let demand = regulator.demand;
+    for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+        if rod.cell_type.rod_group() == Some(RodGroup::Automatic) {
+            rod.insert_rod = true;
+            rod.set_point = demand;
+        }
+    }
+}
+
+fn group_position(mainstruct: &MainStruct, group: RodGroup) -> f32 {
+    let positions: Vec<f32> = mainstruct
+        .absorber_rods
+        .iter()
+        .flatten()
+        .filter(|rod| rod.cell_type.rod_group() == Some(group))
+        .map(|rod| rod.absorber_rod_position)
+        .collect();
+    if positions.is_empty() {
+        return 0.0;
+    }
+    positions.iter().sum::<f32>() / positions.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{send_command, HELP_PAGE_LINES};
+
+    fn regulated(power: f32) -> MainStruct {
+        let mut mainstruct = MainStruct::default();
+        for rod in mainstruct.absorber_rods.iter_mut().flatten() {
+            rod.absorber_rod_position = 50.0;
+        }
+        mainstruct.core.thermal_power = power;
+        enable_regulator(&mut mainstruct);
+        mainstruct
+    }
+
+    #[test]
+    fn test_bumpless_and_deadband() {
+        let mut mainstruct = regulated(80.0);
+        assert_eq!(mainstruct.core.regulator.setpoint, 80.0);
+        mainstruct.core.thermal_power = 80.3;
+        automatic_regulator(&mut mainstruct, 1.0);
+        assert_eq!(mainstruct.core.regulator.demand, 50.0);
+    }
+
+    #[test]
+    fn test_high_power_inserts_at_limited_rate() {
+        let mut mainstruct = regulated(80.0);
+        mainstruct.core.thermal_power = 90.0;
+        automatic_regulator(&mut mainstruct, 1.0);
+        assert_eq!(mainstruct.core.regulator.demand, 50.0 + MAX_RATE);
+        let ar = mainstruct
+            .absorber_rods
+            .iter()
+            .flatten()
+            .find(|rod| rod.cell_type.rod_group() == Some(RodGroup::Automatic))
+            .unwrap();
+        assert!(ar.insert_rod && ar.set_point == 50.0 + MAX_RATE);
+
+        mainstruct.core.thermal_power = 70.0;
+        for _ in 0..10 {
+            automatic_regulator(&mut mainstruct, 1.0);
+        }
+        assert!(mainstruct.core.regulator.demand < 50.0);
+    }
+
+    #[test]
+    fn test_setpoint_out_of_range_is_refused() {
+        let mut mainstruct = regulated(80.0);
+        send_command("auto power 9999", &mut mainstruct, HELP_PAGE_LINES);
+        assert_eq!(mainstruct.core.regulator.setpoint, 80.0);
+        assert!(mainstruct.data.log[0].contains("at most"));
+        send_command("auto power 60", &mut mainstruct, HELP_PAGE_LINES);
+        assert_eq!(mainstruct.core.regulator.setpoint, 60.0);
+    }
+
+    #[test]
+    fn test_scram_takes_rods_back() {
+        let mut mainstruct = regulated(80.0);
+        mainstruct.core.scram = true;
+        automatic_regulator(&mut mainstruct, 1.0);
+        assert!(!mainstruct.core.regulator.enabled);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::geometry::CORE_HEIGHT;
+use crate::layout::RodGroup;
+use crate::structs::AXIAL_NODES;
+
+// lengths along the channel in m
+const ABSORBER_LENGTH: f64 = 6.2;
+// water filled telescopic link between the absorber and the displacer
+const WATER_COLUMN_LENGTH: f64 = 1.25;
+const DISPLACER_LENGTH: f64 = 4.5;
+// worth of water in the rod channel per m, relative to the absorber
+const WATER_WORTH: f64 = 0.15;
+// shortened absorbers come up from below the core, with no displacer,
+// fully inserted they fill the core from the bottom up to this height
+const SHORTENED_ABSORBER_LENGTH: f64 = 3.05;
+// points each node is split into for the worth integrals
+const POINTS_PER_NODE: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Section {
+    Absorber,
+    Water,
+    Displacer,
+}
+
+/// What fills the rod channel at a depth below the top of the core, in m.
+/// Position 0 is fully withdrawn with the absorber just above the core and the displacer
+/// centred in it, leaving a water column above and below, 100 is fully inserted.
+pub fn rod_section(absorber_rod_position: f32, depth: f64) -> Section {
+    let travel = (absorber_rod_position as f64 / 100.0).clamp(0.0, 1.0) * CORE_HEIGHT;
+    let displacer_top = travel + WATER_COLUMN_LENGTH;
+    if depth < travel && depth >= travel - ABSORBER_LENGTH {
+        Section::Absorber
+    } else if depth >= displacer_top && depth < displacer_top + DISPLACER_LENGTH {
+        Section::Displacer
+    } else {
+        Section::Water
+    }
+}
+
+/// Worth of the part of a rod in one axial node, against the same rod fully withdrawn,
+/// in units of a fully inserted absorber. Nodes count from the top of the core.
+pub fn node_rod_worth(absorber_rod_position: f32, node: usize) -> f64 {
+    let node_height = CORE_HEIGHT / AXIAL_NODES as f64;
+    let mut worth = 0.0;
+    for point in 0..POINTS_PER_NODE {
+        let depth = (node as f64 + (point as f64 + 0.5) / POINTS_PER_NODE as f64) * node_height;
+        worth += match rod_section(absorber_rod_position, depth) {
+            Section::Absorber => 1.0,
+            Section::Water => WATER_WORTH,
+            Section::Displacer => 0.0,
+        };
+        if rod_section(0.0, depth) == Section::Water {
+            worth -= WATER_WORTH;
+        }
+    }
+    worth / POINTS_PER_NODE as f64
+}
+
+/// Worth of a shortened absorber in one axial node, in units of a fully inserted absorber.
+/// Withdrawn it sits below the core and the channel above it is all water.
+pub fn shortened_node_worth(absorber_rod_position: f32, node: usize) -> f64 {
+    let node_height = CORE_HEIGHT / AXIAL_NODES as f64;
+    let top = (absorber_rod_position as f64 / 100.0).clamp(0.0, 1.0) * SHORTENED_ABSORBER_LENGTH;
+    let mut worth = 0.0;
+    for point in 0..POINTS_PER_NODE {
+        let depth = (node as f64 + (point as f64 + 0.5) / POINTS_PER_NODE as f64) * node_height;
+        let height = CORE_HEIGHT - depth;
+        if height < top && height >= top - SHORTENED_ABSORBER_LENGTH {
+            worth += 1.0;
+        }
+    }
+    worth / POINTS_PER_NODE as f64
+}
+
+/// Node worth of a rod of any group.
+pub fn group_node_worth(group: RodGroup, absorber_rod_position: f32, node: usize) -> f64 {
+    match group {
+        RodGroup::Shortened => shortened_node_worth(absorber_rod_position, node),
+        _ => node_rod_worth(absorber_rod_position, node),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // driving water out of the bottom of the core with the displacer adds reactivity,
+    // so with the flux low in the core the first metre of travel is negative
+    fn rod_worth(absorber_rod_position: f32, importance: &[f64]) -> f64 {
+        importance
+            .iter()
+            .enumerate()
+            .map(|(node, weight)| weight * node_rod_worth(absorber_rod_position, node))
+            .sum()
+    }
+
+    fn bottom_peaked() -> Vec<f64> {
+        let shape: Vec<f64> = (0..AXIAL_NODES)
+            .map(|j| {
+                let z = (j as f64 + 0.5) / AXIAL_NODES as f64;
+                if z > 0.3 {
+                    (std::f64::consts::PI * (z - 0.3) / 0.7).sin().powi(2)
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let total: f64 = shape.iter().sum();
+        shape.iter().map(|point| point / total).collect()
+    }
+
+    #[test]
+    fn test_rod_sections() {
+        assert_eq!(rod_section(0.0, 0.5), Section::Water);
+        assert_eq!(rod_section(0.0, 3.5), Section::Displacer);
+        assert_eq!(rod_section(0.0, 6.5), Section::Water);
+        assert_eq!(rod_section(100.0, 3.5), Section::Absorber);
+        assert_eq!(rod_section(100.0, 0.5), Section::Water);
+    }
+
+    #[test]
+    fn test_rod_worth_limits() {
+        let importance = bottom_peaked();
+        assert_eq!(rod_worth(0.0, &importance), 0.0);
+        assert!(rod_worth(100.0, &importance) > 0.9);
+    }
+
+    #[test]
+    fn test_shortened_from_below() {
+        // fully in, a shortened absorber covers the bottom half of the core and nothing above
+        assert_eq!(shortened_node_worth(0.0, AXIAL_NODES - 1), 0.0);
+        assert_eq!(shortened_node_worth(100.0, AXIAL_NODES - 1), 1.0);
+        assert_eq!(shortened_node_worth(100.0, 0), 0.0);
+        assert!(shortened_node_worth(50.0, AXIAL_NODES - 1) > 0.0);
+    }
+
+    #[test]
+    fn test_positive_scram_effect() {
+        // the displacer reaches the bottom water before the absorber reaches the flux
+        let importance = bottom_peaked();
+        assert!(rod_worth(10.0, &importance) < 0.0);
+        assert!(rod_worth(50.0, &importance) > 0.0);
+    }
+}
@@ -0,0 +1,334 @@
+use std::fs;
+use std::path::Path;
+
+use crate::commands::{run_command, HELP_PAGE_LINES};
+use crate::snapshot::read_snapshot;
+use crate::startup::average_rod_position;
+use crate::structs::MainStruct;
+use serde::{Deserialize, Serialize};
+
+/// A training run: the plant it starts from, what happens to it and what the operator has to do.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Scenario {
+    /// snapshot is a saved plant to start from, relative to the scenario file, None for a fresh one
+    /// initial are the commands run on it before the clock starts
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub snapshot: Option<String>,
+    #[serde(default)]
+    pub initial: Vec<String>,
+    #[serde(default)]
+    pub events: Vec<Event>,
+    #[serde(default)]
+    pub objectives: Vec<Objective>,
+}
+
+/// Commands run once, when the trigger is first met.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Event {
+    pub trigger: Trigger,
+    pub commands: Vec<String>,
+    #[serde(default)]
+    pub fired: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Trigger {
+    /// at this simulated time, in s
+    At(f64),
+    When(Condition),
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Condition {
+    Above(Quantity, f32),
+    Below(Quantity, f32),
+}
+
+/// What a condition reads, in the units the console shows it in.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Quantity {
+    ThermalPower,
+    Period,
+    StartupRate,
+    Orm,
+    RodPosition,
+    SteamPressure,
+    TurbineSpeed,
+    DeaeratorLevel,
+    CoolantFlow,
+    DrainValve,
+}
+
+pub const QUANTITIES: [Quantity; 10] = [
+    Quantity::ThermalPower,
+    Quantity::Period,
+    Quantity::StartupRate,
+    Quantity::Orm,
+    Quantity::RodPosition,
+    Quantity::SteamPressure,
+    Quantity::TurbineSpeed,
+    Quantity::DeaeratorLevel,
+    Quantity::CoolantFlow,
+    Quantity::DrainValve,
+];
+
+impl Quantity {
+    /// The name conditions are given with at the console.
+    pub fn name(self) -> &'static str {
+        match self {
+            Quantity::ThermalPower => "thermal_power",
+            Quantity::Period => "period",
+            Quantity::StartupRate => "startup_rate",
+            Quantity::Orm => "orm",
+            Quantity::RodPosition => "rod_position",
+            Quantity::SteamPressure => "steam_pressure",
+            Quantity::TurbineSpeed => "turbine_speed",
+            Quantity::DeaeratorLevel => "deaerator_level",
+            Quantity::CoolantFlow => "coolant_flow",
+            Quantity::DrainValve => "drain_valve",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        QUANTITIES
+            .into_iter()
+            .find(|quantity| quantity.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Objective {
+    /// deadline in s of simulated time, Reach has to be met before it and Keep has to hold until it
+    pub description: String,
+    pub goal: Goal,
+    pub deadline: f64,
+    #[serde(default)]
+    pub status: Status,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Goal {
+    Reach(Condition),
+    Keep(Condition),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Status {
+    #[default]
+    Pending,
+    Met,
+    Failed,
+}
+
+pub fn parse_scenario(text: &str) -> Result<Scenario, String> {
+    ron::from_str(text).map_err(|error| error.to_string())
+}
+
+/// Read a scenario file, with its snapshot taken relative to the file.
+pub fn load_scenario(path: &str) -> Result<Scenario, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+    let mut scenario = parse_scenario(&text).map_err(|error| format!("{}: {}", path, error))?;
+    if let Some(snapshot) = &scenario.snapshot {
+        let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+        scenario.snapshot = Some(directory.join(snapshot).to_string_lossy().into_owned());
+    }
+    Ok(scenario)
+}
+
+/// Set the plant up as the scenario starts it and hand the scenario to the clock.
+pub fn start_scenario(mainstruct: &mut MainStruct, scenario: Scenario) -> Result<(), String> {
+    if let Some(snapshot) = &scenario.snapshot {
+        *mainstruct = read_snapshot(Path::new(snapshot))?;
+    }
+    for command in scenario.initial.iter() {
+        run_command(command, mainstruct, HELP_PAGE_LINES);
+    }
+    mainstruct
+        .data
+        .log
+        .insert(0, format!("Scenario: {}", scenario.name));
+    if !scenario.description.is_empty() {
+        mainstruct.data.log.insert(0, scenario.description.clone());
+    }
+    mainstruct.scenario = Some(scenario);
+    Ok(())
+}
+
+/// Fire the events that have come due and settle the objectives, before each step.
+pub fn scenario(mainstruct: &mut MainStruct) {
+    let Some(mut scenario) = mainstruct.scenario.take() else {
+        return;
+    };
+    let time = mainstruct.clock.time;
+
+    let mut commands = Vec::new();
+    for event in scenario.events.iter_mut().filter(|event| !event.fired) {
+        event.fired = match event.trigger {
+            Trigger::At(at) => time >= at,
+            Trigger::When(condition) => holds(mainstruct, condition),
+        };
+        if event.fired {
+            commands.extend(event.commands.iter().cloned());
+        }
+    }
+
+    let pending = scenario
+        .objectives
+        .iter()
+        .any(|objective| objective.status == Status::Pending);
+    for objective in scenario
+        .objectives
+        .iter_mut()
+        .filter(|objective| objective.status == Status::Pending)
+    {
+        objective.status = match objective.goal {
+            Goal::Reach(condition) if holds(mainstruct, condition) => Status::Met,
+            Goal::Reach(_) if time > objective.deadline => Status::Failed,
+            Goal::Keep(condition) if !holds(mainstruct, condition) => Status::Failed,
+            Goal::Keep(_) if time >= objective.deadline => Status::Met,
+            _ => Status::Pending,
+        };
+        match objective.status {
+            Status::Met => mainstruct
+                .data
+                .log
+                .insert(0, format!("Objective met: {}", objective.description)),
+            Status::Failed => mainstruct
+                .data
+                .log
+                .insert(0, format!("Objective failed: {}", objective.description)),
+            Status::Pending => {}
+        }
+    }
+    if pending
+        && scenario
+            .objectives
+            .iter()
+            .all(|objective| objective.status != Status::Pending)
+    {
+        let met = scenario
+            .objectives
+            .iter()
+            .filter(|objective| objective.status == Status::Met)
+            .count();
+        mainstruct.data.log.insert(
+            0,
+            format!(
+                "Scenario complete: {} of {} objectives met",
+                met,
+                scenario.objectives.len()
+            ),
+        );
+    }
+    mainstruct.scenario = Some(scenario);
+
+    for command in commands.iter() {
+        run_command(command, mainstruct, HELP_PAGE_LINES);
+    }
+}
+
+/// Log every objective of the running scenario with how it stands.
+pub fn list_objectives(mainstruct: &mut MainStruct) {
+    let lines: Vec<String> = match &mainstruct.scenario {
+        Some(scenario) => scenario
+            .objectives
+            .iter()
+            .map(|objective| format!("{:?}: {}", objective.status, objective.description))
+            .collect(),
+        None => vec!["No scenario is running".to_string()],
+    };
+    for line in lines {
+        mainstruct.data.log.insert(0, line);
+    }
+}
+
+pub fn holds(mainstruct: &MainStruct, condition: Condition) -> bool {
+    match condition {
+        Condition::Above(quantity, value) => quantity_value(mainstruct, quantity) > value,
+        Condition::Below(quantity, value) => quantity_value(mainstruct, quantity) < value,
+    }
+}
+
+fn quantity_value(mainstruct: &MainStruct, quantity: Quantity) -> f32 {
+    match quantity {
+        Quantity::ThermalPower => mainstruct.core.thermal_power,
+        Quantity::Period => mainstruct.data.period,
+        Quantity::StartupRate => mainstruct.data.startup_rate,
+        Quantity::Orm => mainstruct.core.orm.margin,
+        Quantity::RodPosition => average_rod_position(mainstruct),
+        Quantity::SteamPressure => mainstruct.core.steam.steam_pressure,
+        Quantity::TurbineSpeed => mainstruct.turbine.turbine_speed,
+        Quantity::DeaeratorLevel => mainstruct.deaerator.water_level,
+        Quantity::CoolantFlow => mainstruct.core.coolant_flow,
+        Quantity::DrainValve => mainstruct.core.drain_valve,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::tick;
+
+    #[test]
+    fn test_example_scenario() {
+        let scenario = parse_scenario(include_str!("../resources/scenarios/startup.ron")).unwrap();
+        assert!(!scenario.events.is_empty() && !scenario.objectives.is_empty());
+    }
+
+    #[test]
+    fn test_example_scenario_starts_shut_down() {
+        let mut mainstruct = MainStruct::default();
+        let run = parse_scenario(include_str!("../resources/scenarios/startup.ron")).unwrap();
+        start_scenario(&mut mainstruct, run).unwrap();
+        for _ in 0..300 {
+            tick(&mut mainstruct, 1.0);
+        }
+        let objectives = &mainstruct.scenario.as_ref().unwrap().objectives;
+        assert!(objectives
+            .iter()
+            .all(|objective| objective.status != Status::Failed));
+        assert!(mainstruct.core.thermal_power < 1.0);
+    }
+
+    #[test]
+    fn test_events_and_objectives() {
+        let mut mainstruct = MainStruct::default();
+        let run = parse_scenario(
+            r#"(
+                name: "test",
+                initial: ["coolant flow 80"],
+                events: [
+                    (trigger: At(2), commands: ["drain valve 40"]),
+                    (trigger: When(Above(DrainValve, 30)), commands: ["coolant flow 60"]),
+                ],
+                objectives: [
+                    (description: "flow", goal: Reach(Below(CoolantFlow, 70)), deadline: 10),
+                    (description: "valve", goal: Keep(Below(DrainValve, 10)), deadline: 10),
+                ],
+            )"#,
+        )
+        .unwrap();
+        start_scenario(&mut mainstruct, run).unwrap();
+        assert_eq!(mainstruct.core.coolant_flow, 80.0);
+
+        for time in 0..3 {
+            mainstruct.clock.time = time as f64;
+            scenario(&mut mainstruct);
+        }
+        assert_eq!(mainstruct.core.drain_valve, 40.0);
+        assert_eq!(mainstruct.core.coolant_flow, 80.0);
+        // the valve opening sets off the second event on the next step
+        for time in 3..5 {
+            mainstruct.clock.time = time as f64;
+            scenario(&mut mainstruct);
+        }
+        assert_eq!(mainstruct.core.coolant_flow, 60.0);
+        let objectives = &mainstruct.scenario.as_ref().unwrap().objectives;
+        assert_eq!(objectives[0].status, Status::Met);
+        assert_eq!(objectives[1].status, Status::Failed);
+        assert!(mainstruct.data.log[0].starts_with("Scenario complete: 1 of 2"));
+    }
+}
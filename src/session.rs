@@ -0,0 +1,241 @@
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use crate::commands::{run_command, HELP_PAGE_LINES};
+use crate::layout::layout_checksum;
+use crate::snapshot::SNAPSHOT_VERSION;
+use crate::structs::MainStruct;
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg64Mcg;
+
+// A session file is a header with the seed and the plant it was recorded on, then a
+// `<time> <command>` line for every operator command, appended as it is given so the file
+// holds up to a crash.
+
+/// What a session was recorded on, a replay has to start from the same plant to run the same.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    /// version is the SNAPSHOT_VERSION of the build that recorded it
+    /// layout is the layout_checksum of the core lattice
+    /// scenario is the name of the scenario running, empty for none
+    pub seed: u64,
+    pub version: u32,
+    pub layout: u64,
+    pub scenario: String,
+    pub level: u8,
+}
+
+// lines the header takes at the top of the file
+const HEADER_LINES: usize = 5;
+
+impl Header {
+    fn new(mainstruct: &MainStruct, seed: u64) -> Self {
+        Self {
+            seed,
+            version: SNAPSHOT_VERSION,
+            layout: layout_checksum(mainstruct),
+            scenario: scenario_name(mainstruct),
+            level: mainstruct.level,
+        }
+    }
+
+    fn text(&self) -> String {
+        format!(
+            "seed {}\nversion {}\nlayout {:016x}\nscenario {}\nlevel {}\n",
+            self.seed, self.version, self.layout, self.scenario, self.level
+        )
+    }
+
+    /// Why a replay of the session would not run as recorded on this plant, if it would not.
+    fn check(&self, mainstruct: &MainStruct) -> Result<(), String> {
+        let plant = Header::new(mainstruct, self.seed);
+        if self.version != plant.version {
+            return Err(format!(
+                "recorded with plant version {}, this build is version {}",
+                self.version, plant.version
+            ));
+        }
+        if self.layout != plant.layout {
+            return Err("recorded on another core layout".to_string());
+        }
+        if self.scenario != plant.scenario {
+            return Err(format!(
+                "recorded with scenario '{}', this plant runs '{}'",
+                self.scenario, plant.scenario
+            ));
+        }
+        if self.level != plant.level {
+            return Err(format!(
+                "recorded at level {}, this plant runs at level {}",
+                self.level, plant.level
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn scenario_name(mainstruct: &MainStruct) -> String {
+    mainstruct
+        .scenario
+        .as_ref()
+        .map_or(String::new(), |scenario| scenario.name.clone())
+}
+
+/// Record the operator commands from here on, reseeding the plant so a replay draws the same numbers.
+pub fn start_recording(mainstruct: &mut MainStruct, path: &Path) -> Result<(), String> {
+    let seed: u64 = mainstruct.rng.gen();
+    fs::write(path, Header::new(mainstruct, seed).text())
+        .map_err(|error| format!("{}: {}", path.display(), error))?;
+    mainstruct.rng = Pcg64Mcg::seed_from_u64(seed);
+    mainstruct.session.recording = Some(path.to_path_buf());
+    mainstruct
+        .data
+        .log
+        .insert(0, format!("Recording to {}", path.display()));
+    Ok(())
+}
+
+/// Append a command to the session file with the time it was given at,
+/// and stop recording if the file cannot be written.
+pub fn record_command(mainstruct: &mut MainStruct, command: &str) {
+    let Some(path) = &mainstruct.session.recording else {
+        return;
+    };
+    let written = OpenOptions::new()
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{} {}", mainstruct.clock.time, command));
+    if let Err(error) = written {
+        let message = format!("Recording stopped: {}: {}", path.display(), error);
+        mainstruct.data.log.insert(0, message);
+        mainstruct.session.recording = None;
+    }
+}
+
+/// The header and the timed commands of a session file.
+pub fn parse_session(text: &str) -> Result<(Header, VecDeque<(f64, String)>), String> {
+    let mut lines = text.lines();
+    let mut field = |name: &str| {
+        lines
+            .next()
+            .and_then(|line| line.strip_prefix(name))
+            .map(|value| value.trim_start().to_string())
+            .ok_or(format!("the session has no {} in its header", name))
+    };
+    let seed = field("seed")?;
+    let version = field("version")?;
+    let layout = field("layout")?;
+    let scenario = field("scenario")?;
+    let level = field("level")?;
+    let header = Header {
+        seed: seed.parse().map_err(|_| format!("bad seed {}", seed))?,
+        version: version
+            .parse()
+            .map_err(|_| format!("bad version {}", version))?,
+        layout: u64::from_str_radix(&layout, 16).map_err(|_| format!("bad layout {}", layout))?,
+        scenario,
+        level: level.parse().map_err(|_| format!("bad level {}", level))?,
+    };
+
+    let mut commands = VecDeque::new();
+    for (number, line) in lines.enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let (time, command) = line
+            .split_once(' ')
+            .and_then(|(time, command)| Some((time.parse::<f64>().ok()?, command)))
+            .ok_or_else(|| {
+                format!(
+                    "line {}: expected a time and a command",
+                    number + HEADER_LINES + 1
+                )
+            })?;
+        commands.push_back((time, command.to_string()));
+    }
+    Ok((header, commands))
+}
+
+/// Feed a session file back into a fresh plant, to run as it was recorded.
+pub fn start_replay(mainstruct: &mut MainStruct, path: &Path) -> Result<(), String> {
+    let text =
+        fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    let (header, commands) = parse_session(&text)
+        .and_then(|(header, commands)| header.check(mainstruct).map(|_| (header, commands)))
+        .map_err(|error| format!("{}: {}", path.display(), error))?;
+    mainstruct.rng = Pcg64Mcg::seed_from_u64(header.seed);
+    mainstruct.session.replay = commands;
+    mainstruct
+        .data
+        .log
+        .insert(0, format!("Replaying {}", path.display()));
+    Ok(())
+}
+
+/// Run the recorded commands that have come due, before each step as they were given.
+pub fn replay(mainstruct: &mut MainStruct) {
+    while let Some((time, _)) = mainstruct.session.replay.front() {
+        if *time > mainstruct.clock.time {
+            break;
+        }
+        let (_, command) = mainstruct.session.replay.pop_front().unwrap();
+        run_command(&command, mainstruct, HELP_PAGE_LINES);
+        if mainstruct.session.replay.is_empty() {
+            mainstruct.data.log.insert(0, "Replay finished".to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::tick;
+    use crate::commands::send_command;
+    use crate::level::set_level;
+    use crate::snapshot::snapshot_text;
+
+    #[test]
+    fn test_replay_reproduces_the_run() {
+        let path = std::env::temp_dir().join(format!("rbmk-session-{}", std::process::id()));
+        let mut recorded = MainStruct::default();
+        start_recording(&mut recorded, &path).unwrap();
+        send_command("setpoint 50", &mut recorded, HELP_PAGE_LINES);
+        for _ in 0..3 {
+            tick(&mut recorded, 1.0);
+        }
+        send_command("coolant flow 80", &mut recorded, HELP_PAGE_LINES);
+        for _ in 0..3 {
+            tick(&mut recorded, 1.0);
+        }
+
+        let mut replayed = MainStruct::default();
+        start_replay(&mut replayed, &path).unwrap();
+        for _ in 0..6 {
+            tick(&mut replayed, 1.0);
+        }
+        fs::remove_file(&path).unwrap();
+
+        assert!(replayed.session.replay.is_empty());
+        recorded.data.log.clear();
+        replayed.data.log.clear();
+        assert_eq!(
+            snapshot_text(&replayed).unwrap(),
+            snapshot_text(&recorded).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_replay_refuses_another_plant() {
+        let path = std::env::temp_dir().join(format!("rbmk-header-{}", std::process::id()));
+        let mut recorded = MainStruct::default();
+        start_recording(&mut recorded, &path).unwrap();
+
+        let mut replayed = MainStruct::default();
+        set_level(&mut replayed, 1).unwrap();
+        let refused = start_replay(&mut replayed, &path);
+        fs::remove_file(&path).unwrap();
+        assert!(refused.unwrap_err().contains("recorded at level 3"));
+    }
+}
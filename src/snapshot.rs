@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::structs::MainStruct;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+
+// raise whenever a change to MainStruct would misread the snapshots saved before it
+pub const SNAPSHOT_VERSION: u32 = 5;
+// snapshots are kept here, under the working directory
+const SNAPSHOT_DIR: &str = "saves";
+
+/// The state and the version that saved it, read with IgnoredAny for the state to get the version alone.
+#[derive(Serialize, Deserialize)]
+struct Snapshot<T> {
+    version: u32,
+    state: T,
+}
+
+/// The file a snapshot of this name is saved to.
+pub fn snapshot_path(name: &str) -> PathBuf {
+    PathBuf::from(SNAPSHOT_DIR).join(format!("{}.ron", name))
+}
+
+/// The whole plant state as snapshot text, tagged with SNAPSHOT_VERSION.
+pub fn snapshot_text(mainstruct: &MainStruct) -> Result<String, String> {
+    let snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        state: mainstruct,
+    };
+    ron::to_string(&snapshot).map_err(|error| error.to_string())
+}
+
+/// Read the plant state back from snapshot text of this version.
+pub fn parse_snapshot(text: &str) -> Result<MainStruct, String> {
+    let header: Snapshot<IgnoredAny> = ron::from_str(text).map_err(|error| error.to_string())?;
+    if header.version != SNAPSHOT_VERSION {
+        return Err(format!(
+            "snapshot version {} cannot be read, this build reads version {}",
+            header.version, SNAPSHOT_VERSION
+        ));
+    }
+    let snapshot: Snapshot<MainStruct> = ron::from_str(text).map_err(|error| error.to_string())?;
+    Ok(snapshot.state)
+}
+
+pub fn save_snapshot(mainstruct: &MainStruct, name: &str) -> Result<PathBuf, String> {
+    let path = snapshot_path(name);
+    let text = snapshot_text(mainstruct)?;
+    fs::create_dir_all(SNAPSHOT_DIR).map_err(|error| format!("{}: {}", SNAPSHOT_DIR, error))?;
+    fs::write(&path, text).map_err(|error| format!("{}: {}", path.display(), error))?;
+    Ok(path)
+}
+
+pub fn load_snapshot(name: &str) -> Result<MainStruct, String> {
+    read_snapshot(&snapshot_path(name))
+}
+
+/// Read a snapshot from anywhere, not only from among the saved ones.
+pub fn read_snapshot(path: &Path) -> Result<MainStruct, String> {
+    let text =
+        fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    parse_snapshot(&text).map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::tick;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let mut mainstruct = MainStruct::default();
+        mainstruct.core.scram = true;
+        for _ in 0..3 {
+            tick(&mut mainstruct, 1.0);
+        }
+        let text = snapshot_text(&mainstruct).unwrap();
+        let restored = parse_snapshot(&text).unwrap();
+        assert_eq!(restored.clock.time, 3.0);
+        assert!(restored.core.scram);
+        assert_eq!(snapshot_text(&restored).unwrap(), text);
+    }
+
+    #[test]
+    fn test_other_version_is_refused() {
+        let text = snapshot_text(&MainStruct::default()).unwrap();
+        // the period of a steady core is infinite and has to survive as well
+        assert_eq!(parse_snapshot(&text).unwrap().data.period, f32::INFINITY);
+        for version in [SNAPSHOT_VERSION - 1, SNAPSHOT_VERSION + 1] {
+            let other = text.replacen(
+                &format!("version:{}", SNAPSHOT_VERSION),
+                &format!("version:{}", version),
+                1,
+            );
+            assert_ne!(other, text);
+            assert!(parse_snapshot(&other)
+                .unwrap_err()
+                .contains("cannot be read"));
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use rayon::prelude::*;
+
+use crate::structs::MainStruct;
+
+// source range detector counts per second per unit of population
+const DETECTOR_SENSITIVITY: f64 = 5.0e9;
+// the source range detectors are withdrawn or saturate above this
+const MAX_COUNT_RATE: f64 = 1.0e6;
+
+pub fn neutron_rate(mainstruct: &mut MainStruct) {
+    // count rates span too many decades to read linearly, so the rates are log10 of counts/s
+    let population = mainstruct.core.kinetics.neutron_population;
+    let count_rate = (population * DETECTOR_SENSITIVITY).min(MAX_COUNT_RATE);
+    mainstruct.source_range.count_rate = count_rate as f32;
+    mainstruct.data.neutron_rate = count_rate.max(1.0).log10() as f32;
+
+    mainstruct.absorber_rods.par_iter_mut().for_each(|row| {
+        row.par_iter_mut().for_each(|rod| {
+            let local = (rod.neutron_flux as f64 * DETECTOR_SENSITIVITY).min(MAX_COUNT_RATE);
+            rod.neutron_rate = local.max(1.0).log10() as f32;
+        });
+    });
+}
+
+/// Average position of the absorber rods, in %.
+pub fn average_rod_position(mainstruct: &MainStruct) -> f32 {
+    let rods = mainstruct
+        .absorber_rods
+        .iter()
+        .flatten()
+        .filter(|rod| rod.cell_type.is_rod());
+    rods.clone()
+        .map(|rod| rod.absorber_rod_position)
+        .sum::<f32>()
+        / rods.count() as f32
+}
+
+/// Record 1/M against the average rod position and log it,
+/// with the critical position from the last two points once there are two.
+pub fn record_inverse_count_rate(mainstruct: &mut MainStruct) {
+    let source_range = &mut mainstruct.source_range;
+    if source_range.reference_count_rate <= 0.0 {
+        source_range.reference_count_rate = source_range.count_rate;
+    }
+    let inverse = source_range.reference_count_rate / source_range.count_rate.max(f32::EPSILON);
+    let position = average_rod_position(mainstruct);
+    let source_range = &mut mainstruct.source_range;
+    source_range.inverse_count_rate.push((position, inverse));
+
+    let message = match critical_position(&source_range.inverse_count_rate) {
+        Some(critical) => format!(
+            "1/M: {:.3} at rods {:.1}%, critical near {:.1}%",
+            inverse, position, critical
+        ),
+        None => format!("1/M: {:.3} at rods {:.1}%", inverse, position),
+    };
+    mainstruct.data.log.insert(0, message);
+}
+
+/// Extrapolate the last two 1/M points to zero.
+pub fn critical_position(points: &[(f32, f32)]) -> Option<f32> {
+    if points.len() < 2 {
+        return None;
+    }
+    let (x1, y1) = points[points.len() - 2];
+    let (x2, y2) = points[points.len() - 1];
+    if (y1 - y2).abs() < f32::EPSILON || (x1 - x2).abs() < f32::EPSILON {
+        return None;
+    }
+    Some(x2 - y2 * (x2 - x1) / (y2 - y1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_critical_position() {
+        assert_eq!(critical_position(&[(100.0, 1.0)]), None);
+        let critical = critical_position(&[(100.0, 1.0), (80.0, 0.6)]).unwrap();
+        assert!((critical - 50.0).abs() < 1.0e-3, "critical: {}", critical);
+    }
+
+    #[test]
+    fn test_measurable_when_shut_down() {
+        let mut mainstruct = MainStruct::default();
+        mainstruct.core.kinetics.neutron_population = 1.0e-9;
+        neutron_rate(&mut mainstruct);
+        assert!(mainstruct.source_range.count_rate > 1.0);
+        assert!(mainstruct.data.neutron_rate > 0.0);
+    }
+}
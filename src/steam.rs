@@ -1,11 +1,17 @@
-use crate::structs::MainStruct;
+use rayon::prelude::*;
 
-pub fn steam(mainstruct: &mut MainStruct) {
+use crate::axial::node_average;
+use crate::kinetics::{flux_weighted, NOMINAL_THERMAL_POWER};
+use crate::layout::fuel_channels;
+use crate::level::{enabled, Subsystem};
+use crate::structs::{MainStruct, AXIAL_NODES};
+
+pub fn steam(mainstruct: &mut MainStruct, dt: f32) {
     // calculate steam pressure in MPa, approx FP: 6.9MPa (100%)
     // calculate steam flow rate in kg/s
     // calculate steam temperature in C
 
-    steam_production(mainstruct.core.steam.steam_pressure, mainstruct);
+    steam_production(mainstruct.core.steam.steam_pressure, mainstruct, dt);
     //mainstruct.data.log.push(format!("{}", mainstruct.core.steam.steam_pressure));
     // calulate steam temperature between 100 and 600(superheated) C
     const SATMIN: f32 = 99.9743; // saturation temperature at 0MPa
@@ -16,15 +22,17 @@ pub fn steam(mainstruct: &mut MainStruct) {
     let _superheat_degrees =
         SUPERHEAT - (SATMAX - SATMIN) * mainstruct.core.steam.steam_pressure / 6.9;
 
-    steam_core_turbine(mainstruct);
-    steam_turbine_outside(mainstruct);
-    deaerator_process(mainstruct);
-    condenser_process(mainstruct);
+    steam_core_turbine(mainstruct, dt);
+    steam_turbine_outside(mainstruct, dt);
+    if enabled(mainstruct, Subsystem::WaterInventory) {
+        deaerator_process(mainstruct, dt);
+        condenser_process(mainstruct, dt);
+    }
 
     // flowrate based on diameter of the pipe times drain valve position times velocity
     //mainstruct.core.steam.steam_flow_rate = mainstruct.core.drain_valve * diameter;
 }
-fn steam_core_turbine(mainstruct: &mut MainStruct) {
+fn steam_core_turbine(mainstruct: &mut MainStruct, dt: f32) {
     mainstruct.core.steam.steam_flow_rate = calculate_flowrate(
         mainstruct.core.steam.steam_pressure,
         mainstruct.turbine.steam_pressure,
@@ -33,37 +41,41 @@ fn steam_core_turbine(mainstruct: &mut MainStruct) {
 
     //let flow_factor = mainstruct.core.drain_valve; // flow factor of the pipe
     //mainstruct.data.log.push(format!("D: {}, PR: {}, DP: {}, FF: {}, FR: {}", density, mainstruct.core.steam.steam_pressure, differential_pressure, flow_factor, mainstruct.core.steam.steam_flow_rate));
-    mainstruct.core.steam.steam_pressure -= mainstruct.core.steam.steam_flow_rate;
+    mainstruct.core.steam.steam_pressure -= mainstruct.core.steam.steam_flow_rate * dt;
     if mainstruct.core.steam.steam_pressure < 0.0 {
         mainstruct.core.steam.steam_pressure = 0.0;
     }
-    mainstruct.turbine.steam_pressure += mainstruct.core.steam.steam_flow_rate;
+    mainstruct.turbine.steam_pressure += mainstruct.core.steam.steam_flow_rate * dt;
 }
 
-fn steam_production(steam_pressure: f32, mainstruct: &mut MainStruct) {
+fn steam_production(steam_pressure: f32, mainstruct: &mut MainStruct, dt: f32) {
     // pressure coefficient based on the current pressure
     let pressure_coefficient = steam_pressure * 0.15 + 1.0;
-    // pressure change based on the current thermal power
-    let pressure_change = (mainstruct.core.thermal_power / 100.0 * 600.0 - 100.0).max(0.0) * 0.3
-        / pressure_coefficient;
+    // pressure change based on the current thermal power, decay heat included,
+    // so the core keeps boiling off steam after a shutdown
+    let pressure_change =
+        (mainstruct.core.thermal_power / 100.0 * 500.0).max(0.0) * 0.3 / pressure_coefficient;
 
-    if (mainstruct.core.thermal_power / 100.0 * 600.0 - 100.0) > 0.0 {
-        mainstruct.core.steam.steam_pressure += pressure_change / 500.0;
-    }
+    mainstruct.core.steam.steam_pressure += pressure_change / 500.0 * dt;
 }
-fn steam_turbine_outside(mainstruct: &mut MainStruct) {
+fn steam_turbine_outside(mainstruct: &mut MainStruct, dt: f32) {
     let atmospheric_pressure_mpa = 0.101325;
     mainstruct.turbine.steam_flow_rate = calculate_flowrate(
         mainstruct.turbine.steam_pressure,
         atmospheric_pressure_mpa,
         mainstruct.turbine.steam_drain_valve,
     );
-    mainstruct.turbine.steam_pressure -= mainstruct.turbine.steam_flow_rate;
+    mainstruct.turbine.steam_pressure -= mainstruct.turbine.steam_flow_rate * dt;
     if mainstruct.turbine.steam_pressure < 0.0 {
         mainstruct.turbine.steam_pressure = 0.0;
     }
 }
 
+pub fn saturation_temperature(steam_pressure: f32) -> f32 {
+    const SATMIN: f32 = 99.9743; // saturation temperature at 0MPa
+    const SATMAX: f32 = 286.803; // saturation temperature at 6.9MPa
+    SATMIN + (SATMAX - SATMIN) * steam_pressure.clamp(0.0, 6.9) / 6.9
+}
 pub fn calculate_density(steam_pressure: f32) -> f32 {
     const DENMIN: f32 = 0.251560; // density of steam at 0MPa
     const DENMAX: f32 = 18.2339; // density of steam at 6.9MPa
@@ -79,7 +91,7 @@ pub fn calculate_flowrate(steam_pressure: f32, location_pressure: f32, drain_val
     (differential_pressure.abs() / specific_gravity).sqrt() * (drain_valve) / 10000.0
 }
 
-fn deaerator_process(mainstruct: &mut MainStruct) {
+fn deaerator_process(mainstruct: &mut MainStruct, dt: f32) {
     // Constants
     const DEGAS_RATE: f32 = 0.1; // Adjust this constant based on the actual degas rate in the system
     const WATER_LEVEL_MIN: f32 = 0.0;
@@ -87,14 +99,14 @@ fn deaerator_process(mainstruct: &mut MainStruct) {
     const FEEDWATER_PUMP_COEFFICIENT: f32 = 0.5; // Adjust this constant based on the actual feedwater pump characteristics
 
     // Steam input
-    let steam_input = mainstruct.turbine.steam_flow_rate * DEGAS_RATE;
+    let steam_input = mainstruct.turbine.steam_flow_rate * DEGAS_RATE * dt;
 
     // Update deaerator pressure and temperature based on the steam input
     mainstruct.deaerator.pressure += steam_input * 0.001; // Update this calculation based on the actual relationship between steam input and pressure
     mainstruct.deaerator.temperature += steam_input * 0.01; // Update this calculation based on the actual relationship between steam input and temperature
 
     // Feedwater pump operation
-    let feedwater_flow_rate = mainstruct.deaerator.pressure * FEEDWATER_PUMP_COEFFICIENT;
+    let feedwater_flow_rate = mainstruct.deaerator.pressure * FEEDWATER_PUMP_COEFFICIENT * dt;
 
     // Update deaerator water level based on the steam input and feedwater pump operation
     mainstruct.deaerator.water_level += steam_input - feedwater_flow_rate;
@@ -109,14 +121,14 @@ fn deaerator_process(mainstruct: &mut MainStruct) {
 }
 
 
-fn condenser_process(mainstruct: &mut MainStruct) {
+fn condenser_process(mainstruct: &mut MainStruct, dt: f32) {
     // Constants
     const COOLING_WATER_FLOW_RATE: f32 = 500.0; // Adjust this constant based on the actual cooling water flow rate in the system
     const HEAT_TRANSFER_COEFFICIENT: f32 = 0.001; // Adjust this constant based on the actual heat transfer coefficient in the system
     const MIN_CONDENSER_PRESSURE: f32 = 0.001;
     
     // Steam input from the turbine
-    let steam_input = mainstruct.turbine.steam_flow_rate;
+    let steam_input = mainstruct.turbine.steam_flow_rate * dt;
 
     // Update condenser pressure based on the steam input
     mainstruct.condenser.pressure += steam_input * 0.001; // Update this calculation based on the actual relationship between steam input and pressure
@@ -131,3 +143,55 @@ fn condenser_process(mainstruct: &mut MainStruct) {
     mainstruct.condenser.cooling_water_flow_rate = COOLING_WATER_FLOW_RATE;
     mainstruct.condenser.heat_transfer_coefficient = HEAT_TRANSFER_COEFFICIENT;
 }
+
+pub fn void_fraction(mainstruct: &mut MainStruct, dt: f32) {
+    // enthalpy rise over the channel at 100% power and flow, in units of the latent heat
+    const NOMINAL_ENTHALPY_RISE: f32 = 0.17;
+    // inlet subcooling in units of the latent heat
+    const SUBCOOLING: f32 = 0.02;
+    const WATER_DENSITY_MIN: f32 = 740.0; // saturated water at 6.9MPa
+    const WATER_DENSITY_MAX: f32 = 958.0; // saturated water at 0MPa
+    const VOID_TIME_CONSTANT: f32 = 1.5; // s
+
+    let flow = (mainstruct.core.coolant_flow / 100.0).max(0.01);
+    let pressure = mainstruct.core.steam.steam_pressure.clamp(0.0, 6.9);
+    let water_density =
+        WATER_DENSITY_MAX - (WATER_DENSITY_MAX - WATER_DENSITY_MIN) * pressure / 6.9;
+    let density_ratio = calculate_density(pressure) / water_density;
+    let lag = 1.0 - (-dt / VOID_TIME_CONSTANT).exp();
+    let nominal_channel_power = NOMINAL_THERMAL_POWER / fuel_channels(mainstruct) as f32;
+
+    mainstruct.absorber_rods.par_iter_mut().for_each(|row| {
+        row.par_iter_mut().for_each(|rod| {
+            if !rod.cell_type.is_fuel() {
+                return;
+            }
+            // the coolant enters at the bottom, so a node sees the heat of every node below it
+            let channel_rise =
+                rod.thermal_power_output / nominal_channel_power * NOMINAL_ENTHALPY_RISE / flow;
+            let mut enthalpy = -SUBCOOLING;
+            for node in rod.nodes.iter_mut().rev() {
+                let node_rise = channel_rise * node.flux / AXIAL_NODES as f32;
+                // boiling starts once the subcooling is used up, take the quality mid node
+                let quality = (enthalpy + node_rise / 2.0).clamp(0.0, 1.0);
+                enthalpy += node_rise;
+                // homogeneous flow, void fraction from quality and the phase densities
+                let equilibrium =
+                    quality / (quality + (1.0 - quality) * density_ratio).max(f32::EPSILON);
+                node.void_fraction += (equilibrium - node.void_fraction) * lag;
+            }
+            rod.void_fraction = node_average(rod, |node| node.void_fraction);
+        });
+    });
+
+    let coefficient = mainstruct.core.void_coefficient;
+    mainstruct.core.kinetics.reactivity.void =
+        flux_weighted(mainstruct, |rod, k| {
+            void_worth(coefficient, rod.nodes[k].void_fraction)
+        });
+}
+
+/// Void reactivity of a channel in dk/k, the coefficient is per % of void.
+pub fn void_worth(void_coefficient: f32, void_fraction: f32) -> f64 {
+    (void_coefficient * void_fraction * 100.0) as f64
+}
@@ -1,36 +1,50 @@
-use tui::{style::Color, widgets::ListItem};
+use crate::layout::{apply_layout, parse_layout, CellType, RodGroup, DEFAULT_LAYOUT, ROD_GROUPS};
+use crate::level::MAX_LEVEL;
+use crate::malfunction::Malfunction;
+use crate::scenario::Scenario;
+use rand::SeedableRng;
+use rand_pcg::Pcg64Mcg;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FuelRodData {
-    fuel_pellet: FuelPellet,
-    cladding: Cladding,
+    pub fuel_pellet: FuelPellet,
+    pub cladding: Cladding,
 }
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FuelPellet {
     /// U235 and U238 composition
     /// OM = Oxide mass
     /// density = g/cm^3
     /// Diameter = cm
     /// Stack_length = m
-    u_composition: UComposition,
+    pub u_composition: UComposition,
     om: f32,
-    density: f32,
-    diameter: f32,
-    stack_length: f32,
+    pub density: f32,
+    pub diameter: f32,
+    pub stack_length: f32,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct UComposition {
-    u235_composition: f32,
-    u238_composition: f32,
+    pub u235_composition: f32,
+    pub u238_composition: f32,
 }
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Cladding {
+    /// inner_diameter = cm
+    /// thickness = cm
     material: String,
-    inner_diameter: f32,
-    thickness: f32,
+    pub inner_diameter: f32,
+    pub thickness: f32,
 }
 impl Default for Cladding {
     fn default() -> Self {
         Self {
-            material: "Zircaloy".to_string(),
-            inner_diameter: 7.62,
-            thickness: 0.127,
+            material: "Zr-1%Nb".to_string(),
+            inner_diameter: 1.18,
+            thickness: 0.09,
         }
     }
 }
@@ -49,11 +63,12 @@ impl Default for FuelPellet {
             u_composition: UComposition::default(),
             om: 3.0,
             density: 10.7,
-            diameter: 7.62,
+            diameter: 1.152,
             stack_length: 3.65,
         }
     }
 }
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PhysicalVariables {
     pub fuel_rod_data: FuelRodData,
     pub distance_between_c_and_f_rods: f32,
@@ -69,37 +84,259 @@ impl Default for PhysicalVariables {
         }
     }
 }
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Core {
     pub width: u16,
     pub height: u16,
-    // slow, medium, fast
-    pub speed_setpoint: f32,
+    /// rod_drives of each rod group, in the order of ROD_GROUPS
+    /// scram is set from a scram until it is reset
+    pub rod_drives: [RodDrive; ROD_GROUPS.len()],
+    pub scram: bool,
     pub neutron_flux: f32,
+    /// thermal_power is fission_power less the share that comes out later as decay_heat, plus decay_heat, all in % of nominal
     pub thermal_power: f32,
+    pub fission_power: f32,
+    pub decay_heat: f32,
     pub steam: Steam,
     pub rate_of_change: f32,
     pub drain_valve: f32,
     pub drain_setpoint: f32,
     pub selected_rod: usize,
+    pub kinetics: Kinetics,
+    /// coolant_flow of the main circulation pumps in % of nominal
+    /// void_coefficient in dk/k per % of void
+    /// doppler_coefficient in dk/k per C of fuel temperature
+    /// lattice_pitch is the width of one cell of absorber_rods in cm
+    pub coolant_flow: f32,
+    pub void_coefficient: f32,
+    pub doppler_coefficient: f32,
+    pub lattice_pitch: f32,
+    pub diffusion: Diffusion,
+    pub orm: Orm,
+    pub regulator: Regulator,
+    pub local_regulation: LocalRegulation,
 }
 impl Default for Core {
     fn default() -> Self {
         Self {
-            speed_setpoint: 1.0,
-            width: 5,
-            height: 5,
+            rod_drives: ROD_GROUPS.map(RodDrive::new),
+            scram: false,
+            width: 0,
+            height: 0,
             neutron_flux: 0.0,
             thermal_power: 0.0,
+            fission_power: 0.0,
+            decay_heat: 0.0,
             steam: Steam::default(),
             rate_of_change: 0.0,
             drain_valve: 0.0,
             drain_setpoint: 0.0,
             selected_rod: 0,
+            kinetics: Kinetics::default(),
+            coolant_flow: 100.0,
+            void_coefficient: 1.0e-4,
+            doppler_coefficient: -1.2e-5,
+            lattice_pitch: 25.0,
+            diffusion: Diffusion::default(),
+            orm: Orm::default(),
+            regulator: Regulator::default(),
+            local_regulation: LocalRegulation::default(),
         }
     }
 }
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct RodDrive {
+    /// speed of the drive in % of the travel per s
+    /// scram is whether the group goes in on a scram
+    /// individual is whether single rods of the group take commands, or only the whole group
+    pub speed: f32,
+    pub scram: bool,
+    pub individual: bool,
+}
+impl RodDrive {
+    pub fn new(group: RodGroup) -> Self {
+        match group {
+            RodGroup::Manual => Self {
+                speed: 1.0,
+                scram: true,
+                individual: true,
+            },
+            RodGroup::Automatic | RodGroup::LocalAutomatic => Self {
+                speed: 2.0,
+                scram: true,
+                individual: false,
+            },
+            RodGroup::Shortened => Self {
+                speed: 1.0,
+                scram: true,
+                individual: true,
+            },
+            // the emergency rods are kept out and only ever driven all together
+            RodGroup::Emergency => Self {
+                speed: 5.7,
+                scram: true,
+                individual: false,
+            },
+        }
+    }
+}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Orm {
+    /// margin is the operational reactivity margin in equivalent fully inserted rods
+    /// below warning operation is restricted, below minimum the reactor must be shut down
+    pub margin: f32,
+    pub warning: f32,
+    pub minimum: f32,
+}
+impl Default for Orm {
+    fn default() -> Self {
+        Self {
+            margin: 0.0,
+            warning: 26.0,
+            minimum: 15.0,
+        }
+    }
+}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Regulator {
+    /// enabled while the AR rods hold the power, off they stay where they were left
+    /// setpoint is the thermal power held, in % of nominal
+    /// demand is the AR rod position the loop drives to, integral its integral part
+    pub enabled: bool,
+    pub setpoint: f32,
+    pub demand: f32,
+    pub integral: f32,
+}
+impl Default for Regulator {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            setpoint: 100.0,
+            demand: 0.0,
+            integral: 0.0,
+        }
+    }
+}
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LocalRegulation {
+    /// enabled while the LAR rods flatten the power, off they stay where they were left
+    /// zones of the lattice, one around each LAR rod
+    pub enabled: bool,
+    pub zones: Vec<Zone>,
+}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Zone {
+    /// rod is the cell of the LAR rod, every cell nearer to it than to another belongs to the zone
+    /// detectors are the cells of the in-core detectors beside the rod
+    /// reading is the power the detectors see against the average over the zones
+    /// demand is the rod position the zone drives to
+    pub rod: (usize, usize),
+    pub detectors: Vec<(usize, usize)>,
+    pub reading: f32,
+    pub demand: f32,
+}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Kinetics {
+    /// neutron_population relative to full power (1.0 = 100%)
+    /// precursors are the six delayed neutron precursor groups, in the same units
+    /// generation_time is the prompt neutron generation time in s
+    /// reactivity is the balance of every reactivity contribution in dk/k
+    /// source is the external neutron source (photoneutrons, spontaneous fission) in population/s
+    pub neutron_population: f64,
+    pub precursors: [f64; 6],
+    pub generation_time: f64,
+    pub reactivity: Reactivity,
+    pub source: f64,
+}
+impl Default for Kinetics {
+    fn default() -> Self {
+        // start from a small population with the precursors in equilibrium
+        const NEUTRON_POPULATION: f64 = 1.0e-6;
+        const GENERATION_TIME: f64 = 1.0e-3;
+        let mut precursors = [0.0; 6];
+        for (i, precursor) in precursors.iter_mut().enumerate() {
+            *precursor = DELAYED_GROUPS[i].0 / (DELAYED_GROUPS[i].1 * GENERATION_TIME)
+                * NEUTRON_POPULATION;
+        }
+        Self {
+            neutron_population: NEUTRON_POPULATION,
+            precursors,
+            generation_time: GENERATION_TIME,
+            reactivity: Reactivity::default(),
+            source: 1.0e-7,
+        }
+    }
+}
+/// Delayed neutron group fractions and decay constants (1/s) for U-235 (Keepin)
+pub const DELAYED_GROUPS: [(f64, f64); 6] = [
+    (0.000215, 0.0124),
+    (0.001424, 0.0305),
+    (0.001274, 0.111),
+    (0.002568, 0.301),
+    (0.000748, 1.14),
+    (0.000273, 3.01),
+];
+/// Decay heat group fractions of the operating power and decay constants (1/s),
+/// fitted to the ANS-5.1 curve for U-235 after long operation
+pub const DECAY_HEAT_GROUPS: [(f64, f64); 8] = [
+    (0.00466, 1.0),
+    (0.01603, 1.0e-1),
+    (0.01701, 1.0e-2),
+    (0.01092, 1.0e-3),
+    (0.00731, 1.0e-4),
+    (0.00442, 1.0e-5),
+    (0.00187, 1.0e-6),
+    (0.00238, 1.0e-7),
+];
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Diffusion {
+    /// eigenvalue is k-effective from the last solve
+    /// fast_flux and thermal_flux per cell, normalized to an average of 1 over the fuel channels
+    /// power_shape is the fission power per cell, normalized to an average of 1 over the fuel channels
+    pub eigenvalue: f64,
+    pub fast_flux: Vec<Vec<f64>>,
+    pub thermal_flux: Vec<Vec<f64>>,
+    pub power_shape: Vec<Vec<f64>>,
+}
+impl Default for Diffusion {
+    fn default() -> Self {
+        Self {
+            eigenvalue: 1.0,
+            fast_flux: Vec::new(),
+            thermal_flux: Vec::new(),
+            power_shape: Vec::new(),
+        }
+    }
+}
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Reactivity {
+    /// rods: flux weighted reactivity of the absorber rods in dk/k
+    /// xenon: flux weighted xenon-135 poisoning in dk/k
+    /// void: flux weighted steam void feedback in dk/k
+    /// doppler: flux weighted fuel temperature feedback in dk/k
+    /// graphite: flux weighted moderator temperature feedback in dk/k
+    /// burnup: flux weighted depletion of the fuel since it was fresh in dk/k
+    /// lattice: excess reactivity of the clean lattice less leakage, the rest of k-effective
+    pub rods: f64,
+    pub xenon: f64,
+    pub void: f64,
+    pub doppler: f64,
+    pub graphite: f64,
+    pub burnup: f64,
+    pub lattice: f64,
+}
+impl Reactivity {
+    pub fn total(&self) -> f64 {
+        self.rods
+            + self.xenon
+            + self.void
+            + self.doppler
+            + self.graphite
+            + self.burnup
+            + self.lattice
+    }
+}
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Turbine {
     /// turbine_speed in RPM (0-3600)
     /// speed_step in RPM (0(stop), 900, 1800, 2700, 3600)
@@ -133,7 +370,7 @@ impl Default for Turbine {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MainStruct {
     pub core: Core,
     pub absorber_rods: Vec<Vec<FuelRod>>,
@@ -141,35 +378,118 @@ pub struct MainStruct {
     pub turbine: Turbine,
     pub deaerator: Deaerator,
     pub condenser: Condenser,
+    pub moderator: Moderator,
+    pub physical_variables: PhysicalVariables,
+    pub source_range: SourceRange,
+    pub clock: Clock,
+    pub scenario: Option<Scenario>,
+    pub malfunctions: Vec<Malfunction>,
+    /// level is how much of the plant is simulated, from 0 for the core and turbine to 3 for all of it
+    /// rng is where every random number is drawn from, so a seed gives the same run again
+    /// session is the recording or replay running, not part of the plant and not saved with it
+    pub level: u8,
+    pub rng: Pcg64Mcg,
+    #[serde(skip)]
+    pub session: Session,
 }
 impl Default for MainStruct {
     fn default() -> Self {
-        Self {
+        let mut mainstruct = Self {
             core: Core::default(),
-            absorber_rods: vec![vec![FuelRod::default(); 5]; 5],
+            absorber_rods: Vec::new(),
             data: Data::default(),
             turbine: Turbine::default(),
             deaerator: Deaerator::default(),
             condenser: Condenser::default(),
+            moderator: Moderator::default(),
+            physical_variables: PhysicalVariables::default(),
+            source_range: SourceRange::default(),
+            clock: Clock::default(),
+            scenario: None,
+            malfunctions: Vec::new(),
+            level: MAX_LEVEL,
+            rng: Pcg64Mcg::from_entropy(),
+            session: Session::default(),
+        };
+        let layout = parse_layout(DEFAULT_LAYOUT).unwrap();
+        apply_layout(&mut mainstruct, &layout);
+        mainstruct
+    }
+}
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SourceRange {
+    /// count_rate of the source range detectors in counts/s
+    /// reference_count_rate is the count rate at the start of an approach to criticality
+    /// inverse_count_rate holds the recorded (average rod position, 1/M) points
+    pub count_rate: f32,
+    pub reference_count_rate: f32,
+    pub inverse_count_rate: Vec<(f32, f32)>,
+}
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    /// recording is the session file operator commands are appended to
+    /// replay holds the recorded commands still to run, with the times they were given at
+    pub recording: Option<PathBuf>,
+    pub replay: VecDeque<(f64, String)>,
+}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Clock {
+    /// dt is the fixed step every model advances by, in s
+    /// time is the simulated time since the start, in s
+    /// speed is how many simulated seconds pass per second of wall time
+    /// paused stops the clock, steps are still run from pending_steps
+    /// accumulated is simulated time due but not yet run, in s
+    pub dt: f32,
+    pub time: f64,
+    pub speed: f32,
+    pub paused: bool,
+    pub pending_steps: u32,
+    pub accumulated: f32,
+}
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            dt: 1.0,
+            time: 0.0,
+            speed: 1.0,
+            paused: false,
+            pending_steps: 0,
+            accumulated: 0.0,
         }
     }
 }
-type Item<'a> = (ListItem<'a>, Vec<ListItem<'a>>, bool);
+/// A checklist entry, its sub items and whether they are shown
+type Item = (String, Vec<String>, bool);
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Data {
     pub graphs: Vec<Vec<(f64, f64)>>,
     pub reactivity: f32,
     pub neutron_flux: f32,
     pub neutron_rate: f32,
+    /// period of the reactor in s, negative while the power falls
+    /// startup_rate in decades per minute
+    /// alarms that are active now
+    pub period: f32,
+    pub startup_rate: f32,
+    pub alarms: Vec<String>,
     pub log: Vec<String>,
     pub left_tab_index: usize,
     pub left_tab_length: i32,
     pub checklist_selected: usize,
     pub checklist_length: usize,
     pub text_input: bool,
-    pub items: Vec<Item<'static>>,
-    pub selected_item: ListItem<'static>,
+    pub items: Vec<Item>,
+    pub selected_item: String,
+    pub map_view: MapView,
+}
+
+/// What the core map colours the cells by
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MapView {
+    Fuel,
+    Centerline,
+    Cladding,
 }
 
 impl Default for Data {
@@ -178,13 +498,16 @@ impl Default for Data {
             graphs: vec![
                 vec![(0.0, 0.0); 2],
                 vec![(0.0, 0.0); 2],
-                vec![(-15.0, 0.0); 2],
+                vec![(0.0, 0.0); 2],
                 vec![(0.0, 0.0); 2],
                 vec![(0.0, 0.0); 2],
             ],
             reactivity: 0.0,
             neutron_flux: 0.0,
-            neutron_rate: -15.0,
+            neutron_rate: 0.0,
+            period: f32::INFINITY,
+            startup_rate: 0.0,
+            alarms: Vec::new(),
             log: Vec::new(),
             left_tab_index: 0,
             left_tab_length: 3,
@@ -193,56 +516,160 @@ impl Default for Data {
             text_input: true,
             items: vec![
                 (
-                    ListItem::new("Core"),
+                    "Core".to_string(),
                     vec![
-                        ListItem::new("    Reactivity"),
-                        ListItem::new("    Neutron Flux"),
-                        ListItem::new("    Neutron Rate"),
+                        "    Reactivity".to_string(),
+                        "    Neutron Flux".to_string(),
+                        "    Neutron Rate".to_string(),
                     ],
                     false,
                 ),
                 (
-                    ListItem::new("Turbine"),
+                    "Turbine".to_string(),
                     vec![
-                        ListItem::new("    Turbine Speed"),
-                        ListItem::new("    Steam Flow Rate"),
-                        ListItem::new("    Steam Pressure"),
+                        "    Turbine Speed".to_string(),
+                        "    Steam Flow Rate".to_string(),
+                        "    Steam Pressure".to_string(),
                     ],
                     false,
                 ),
                 (
-                    ListItem::new("Steam"),
+                    "Steam".to_string(),
                     vec![
-                        ListItem::new("    Steam Temperature"),
-                        ListItem::new("    Steam Pressure"),
-                        ListItem::new("    Steam Flow Rate"),
+                        "    Steam Temperature".to_string(),
+                        "    Steam Pressure".to_string(),
+                        "    Steam Flow Rate".to_string(),
                     ],
                     false,
                 ),
             ],
-            selected_item: ListItem::new("Core"),
+            selected_item: "Core".to_string(),
+            map_view: MapView::Fuel,
+        }
+    }
+}
+
+/// Axial nodes per channel, from the top of the core down
+pub const AXIAL_NODES: usize = 14;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct AxialNode {
+    /// flux relative to the channel average
+    /// fuel_temperature is the pellet average in C, the radial profile runs from the
+    /// centerline_temperature through the pellet surface to the inner and outer cladding
+    /// void_fraction of the coolant (0-1)
+    /// iodine and xenon are the I-135 and Xe-135 concentrations in atoms/cm^3
+    /// reactivity is the local reactivity of the node in dk/k, rod_reactivity the part of it from the rods around
+    pub flux: f32,
+    pub fuel_temperature: f32,
+    pub centerline_temperature: f32,
+    pub surface_temperature: f32,
+    pub cladding_inner_temperature: f32,
+    pub cladding_outer_temperature: f32,
+    pub void_fraction: f32,
+    pub iodine: f32,
+    pub xenon: f32,
+    pub reactivity: f32,
+    pub rod_reactivity: f32,
+}
+impl Default for AxialNode {
+    fn default() -> Self {
+        Self {
+            flux: 1.0,
+            fuel_temperature: 20.0,
+            centerline_temperature: 20.0,
+            surface_temperature: 20.0,
+            cladding_inner_temperature: 20.0,
+            cladding_outer_temperature: 20.0,
+            void_fraction: 0.0,
+            iodine: 0.0,
+            xenon: 0.0,
+            reactivity: 0.0,
+            rod_reactivity: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Isotopes {
+    /// atoms per heavy metal atom loaded in the fresh fuel
+    /// fission_products counts the fissions so far, each leaving a pair of lumped fission products
+    pub u235: f64,
+    pub u238: f64,
+    pub pu239: f64,
+    pub pu240: f64,
+    pub pu241: f64,
+    pub fission_products: f64,
+}
+impl Default for Isotopes {
+    fn default() -> Self {
+        let u_composition = UComposition::default();
+        Self {
+            u235: u_composition.u235_composition as f64,
+            u238: u_composition.u238_composition as f64,
+            pu239: 0.0,
+            pu240: 0.0,
+            pu241: 0.0,
+            fission_products: 0.0,
         }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct FuelRod {
+    /// neutron_flux relative to the nominal full power flux, averaged over the channel
+    /// iodine, xenon, void_fraction and fuel_temperature are averages of the axial nodes
+    /// centerline_temperature and cladding_temperature are the hottest nodes of the channel in C
+    /// graphite_temperature of the moderator block around the channel in C
+    /// fission_power of the channel in MW, thermal_power_output is what reaches the coolant
+    /// decay_heat of the channel in MW, decay_groups is the share of each decay heat group
+    /// thermal_power_output of the channel in MW
+    /// burnup of the fuel in MWd/kgU, isotopes its composition
+    /// reactivity is the local reactivity of the cell in dk/k
+    /// neutron_rate is log10 of the local detector count rate in counts/s
+    /// nodes split the channel along the fuel stack
+    /// cell_type is what occupies the cell, absorber_rod_position only matters for rods
     pub absorber_rod_position: f32,
+    pub neutron_flux: f32,
+    pub iodine: f32,
+    pub xenon: f32,
+    pub void_fraction: f32,
     pub fuel_temperature: f32,
+    pub centerline_temperature: f32,
+    pub cladding_temperature: f32,
+    pub graphite_temperature: f32,
+    pub fission_power: f32,
+    pub decay_heat: f32,
+    pub decay_groups: [f64; DECAY_HEAT_GROUPS.len()],
     pub thermal_power_output: f32,
+    pub burnup: f64,
+    pub isotopes: Isotopes,
     pub insert_rod: bool,
     pub set_point: f32,
     pub reactivity: f32,
     pub neutron_rate: f32,
     pub neighbors: ([(u16, u16); 4], [bool; 4]),
-    pub temperature_color: Color,
+    pub nodes: [AxialNode; AXIAL_NODES],
+    pub cell_type: CellType,
 }
 impl Default for FuelRod {
     fn default() -> Self {
         Self {
             absorber_rod_position: 100.0,
-            fuel_temperature: 0.0,
+            neutron_flux: 0.0,
+            iodine: 0.0,
+            xenon: 0.0,
+            void_fraction: 0.0,
+            fuel_temperature: 20.0,
+            centerline_temperature: 20.0,
+            cladding_temperature: 20.0,
+            graphite_temperature: 20.0,
+            fission_power: 0.0,
+            decay_heat: 0.0,
+            decay_groups: [0.0; DECAY_HEAT_GROUPS.len()],
             thermal_power_output: 0.0,
+            burnup: 0.0,
+            isotopes: Isotopes::default(),
             insert_rod: true,
             set_point: 0.0,
             reactivity: 0.0,
@@ -251,12 +678,13 @@ impl Default for FuelRod {
                 [(0, 0), (0, 0), (0, 0), (0, 0)],
                 [false, false, false, false],
             ),
-            temperature_color: Color::Reset,
+            nodes: [AxialNode::default(); AXIAL_NODES],
+            cell_type: CellType::Fuel,
         }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Steam {
     /// steam flow rate in kg/s
     /// steam pressure in MPa
@@ -284,7 +712,7 @@ impl Default for Steam {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Deaerator {
     pub pressure: f32,
     pub temperature: f32,
@@ -301,7 +729,23 @@ impl Default for Deaerator {
     
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Moderator {
+    /// temperature_coefficient in dk/k per C of graphite temperature
+    /// average_temperature of the graphite stack in C
+    pub temperature_coefficient: f32,
+    pub average_temperature: f32,
+}
+impl Default for Moderator {
+    fn default() -> Self {
+        Self {
+            temperature_coefficient: 1.0e-5,
+            average_temperature: 20.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Condenser {
     pub pressure: f32,
     pub temperature: f32,
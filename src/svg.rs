@@ -6,7 +6,8 @@ use std::{
 use lazy_static::lazy_static;
 use regex::Regex;
 
-use crate::{structs::MainStruct, arcfm::SvgPoints};
+use crate::arcfm::SvgPoints;
+use rbmk::structs::MainStruct;
 lazy_static! {
     static ref FILL_RE: Regex = Regex::new(r"fill:\s*rgb\((\d+),\s*(\d+),\s*(\d+)\);").unwrap();
     static ref RE: Regex = Regex::new(r"[A-Z]\s*((?:\d+\.\d+\s+)*\d+\.\d+)*").unwrap();
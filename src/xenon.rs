@@ -0,0 +1,116 @@
+use rayon::prelude::*;
+
+use crate::axial::node_average;
+use crate::kinetics::flux_weighted;
+use crate::level::{enabled, Subsystem};
+use crate::structs::MainStruct;
+
+// thermal flux at 100% power in n/cm^2/s
+pub const NOMINAL_FLUX: f32 = 3.0e13;
+// macroscopic fission cross section of the lattice in 1/cm
+const FISSION_CROSS_SECTION: f32 = 0.003;
+// neutrons per fission, at critical the absorption rate matches nu * fission rate
+const NU: f32 = 2.43;
+// fission yields
+const IODINE_YIELD: f32 = 0.0639;
+const XENON_YIELD: f32 = 0.00237;
+// decay constants in 1/s
+const IODINE_DECAY: f32 = 2.87e-5;
+const XENON_DECAY: f32 = 2.09e-5;
+// Xe-135 microscopic absorption cross section in cm^2
+const XENON_ABSORPTION: f32 = 2.65e-18;
+
+pub fn xenon(mainstruct: &mut MainStruct, dt: f32) {
+    // without xenon the core holds no poison, and builds it up from none when it is back on
+    let simulated = enabled(mainstruct, Subsystem::Xenon);
+    mainstruct.absorber_rods.par_iter_mut().for_each(|row| {
+        row.par_iter_mut().for_each(|rod| {
+            // only the fuel makes iodine and xenon
+            if !rod.cell_type.is_fuel() {
+                return;
+            }
+            if !simulated {
+                for node in rod.nodes.iter_mut() {
+                    node.iodine = 0.0;
+                    node.xenon = 0.0;
+                }
+                rod.iodine = 0.0;
+                rod.xenon = 0.0;
+                return;
+            }
+            let channel_flux = rod.neutron_flux;
+            for node in rod.nodes.iter_mut() {
+                let flux = channel_flux * node.flux * NOMINAL_FLUX;
+                let fission_rate = FISSION_CROSS_SECTION * flux;
+
+                // both chains are linear over a step, so integrate them exactly
+                let iodine_equilibrium = IODINE_YIELD * fission_rate / IODINE_DECAY;
+                let iodine = iodine_equilibrium
+                    + (node.iodine - iodine_equilibrium) * (-IODINE_DECAY * dt).exp();
+                let average_iodine = (node.iodine + iodine) / 2.0;
+
+                let removal = XENON_DECAY + XENON_ABSORPTION * flux;
+                let xenon_equilibrium =
+                    (XENON_YIELD * fission_rate + IODINE_DECAY * average_iodine) / removal;
+                node.xenon =
+                    xenon_equilibrium + (node.xenon - xenon_equilibrium) * (-removal * dt).exp();
+                node.iodine = iodine;
+            }
+            rod.iodine = node_average(rod, |node| node.iodine);
+            rod.xenon = node_average(rod, |node| node.xenon);
+        });
+    });
+
+    // weight each channel by its flux, so poison in a dead channel counts for little
+    mainstruct.core.kinetics.reactivity.xenon =
+        flux_weighted(mainstruct, |rod, k| xenon_worth(rod.nodes[k].xenon));
+}
+
+/// Reactivity of a xenon concentration in dk/k, always negative.
+pub fn xenon_worth(xenon: f32) -> f64 {
+    -(XENON_ABSORPTION * xenon / (NU * FISSION_CROSS_SECTION)) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_flux(mainstruct: &mut MainStruct, flux: f32) {
+        for row in mainstruct.absorber_rods.iter_mut() {
+            for rod in row.iter_mut() {
+                rod.neutron_flux = flux;
+            }
+        }
+    }
+
+    #[test]
+    fn test_equilibrium_worth() {
+        let mut mainstruct = MainStruct::default();
+        set_flux(&mut mainstruct, 1.0);
+        for _ in 0..200 {
+            xenon(&mut mainstruct, 3600.0);
+        }
+        let worth = mainstruct.core.kinetics.reactivity.xenon;
+        assert!(worth < -0.015 && worth > -0.03, "worth: {}", worth);
+    }
+
+    #[test]
+    fn test_iodine_pit() {
+        let mut mainstruct = MainStruct::default();
+        set_flux(&mut mainstruct, 1.0);
+        for _ in 0..200 {
+            xenon(&mut mainstruct, 3600.0);
+        }
+        let equilibrium = mainstruct.absorber_rods[24][24].xenon;
+
+        // after a shutdown xenon keeps building from the iodine for several hours
+        set_flux(&mut mainstruct, 0.0);
+        let mut peak = equilibrium;
+        for _ in 0..24 {
+            xenon(&mut mainstruct, 3600.0);
+            peak = peak.max(mainstruct.absorber_rods[24][24].xenon);
+        }
+        assert!(peak > equilibrium * 1.5);
+        assert!(mainstruct.absorber_rods[24][24].xenon < peak);
+    }
+}